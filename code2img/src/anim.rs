@@ -0,0 +1,134 @@
+use crate::render::{line_chars, Line, Metrics};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, DynamicImage, Frame, Rgb, RgbImage};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+/// A rendered animation frame and how long it stays on screen.
+pub struct AnimFrame {
+    pub image: RgbImage,
+    pub delay_ms: u32,
+}
+
+/// Write `frames` as a looping animation. `.gif` outputs are GIFs; anything
+/// else (`.png`, `.apng`) is written as an animated PNG.
+pub fn save(path: &str, frames: Vec<AnimFrame>) {
+    let is_gif = Path::new(path)
+        .extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("gif"));
+    let file = BufWriter::new(File::create(path).expect("Failed to create output file"));
+    if is_gif {
+        save_gif(file, frames);
+    } else {
+        save_apng(file, frames);
+    }
+}
+
+fn save_gif(file: BufWriter<File>, frames: Vec<AnimFrame>) {
+    let mut encoder = GifEncoder::new_with_speed(file, 10);
+    encoder
+        .set_repeat(Repeat::Infinite)
+        .expect("Failed to write GIF");
+    for frame in frames {
+        let rgba = DynamicImage::ImageRgb8(frame.image).into_rgba8();
+        let delay = Delay::from_numer_denom_ms(frame.delay_ms, 1);
+        encoder
+            .encode_frame(Frame::from_parts(rgba, 0, 0, delay))
+            .expect("Failed to write GIF");
+    }
+}
+
+fn save_apng(file: BufWriter<File>, frames: Vec<AnimFrame>) {
+    let (width, height) = frames
+        .first()
+        .map(|f| f.image.dimensions())
+        .expect("Animation has no frames");
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .expect("Failed to write APNG");
+    let mut writer = encoder.write_header().expect("Failed to write APNG");
+    for frame in frames {
+        let delay = frame.delay_ms.min(u16::MAX as u32) as u16;
+        writer
+            .set_frame_delay(delay, 1000)
+            .expect("Failed to write APNG");
+        writer
+            .write_image_data(frame.image.as_raw())
+            .expect("Failed to write APNG");
+    }
+    writer.finish().expect("Failed to write APNG");
+}
+
+/// Settings for the `--animate typing` effect.
+pub struct Typing {
+    pub chars_per_second: f32,
+    pub cursor_blink: bool,
+    pub hold_ms: u32,
+}
+
+/// Blink half-period for the cursor during the final hold.
+const BLINK_MS: u32 = 500;
+/// Shortest frame delay most GIF viewers honor.
+const MIN_FRAME_MS: u32 = 20;
+
+/// Frames in which the highlighted `lines` appear progressively. The final
+/// frame has no cursor and is identical to the static render.
+pub fn typing_frames(
+    metrics: &Metrics,
+    lines: &[Line],
+    bg: Rgb<u8>,
+    cursor_color: Rgb<u8>,
+    typing: &Typing,
+) -> Vec<AnimFrame> {
+    let (width, height) = metrics.canvas_size(lines);
+    let total: usize = lines.iter().map(line_chars).sum();
+
+    let cps = typing.chars_per_second.max(1.0);
+    let frame_ms = ((1000.0 / cps) as u32).max(MIN_FRAME_MS);
+    let chars_per_frame = ((cps * frame_ms as f32 / 1000.0).round() as usize).max(1);
+
+    let render = |reveal: usize, cursor: bool| {
+        let mut img = RgbImage::from_pixel(width, height, bg);
+        let pos = metrics.draw_lines(&mut img, lines, Some(reveal));
+        if cursor {
+            metrics.draw_cursor(&mut img, pos, cursor_color);
+        }
+        img
+    };
+
+    let mut frames = Vec::new();
+    let mut reveal = 0;
+    while reveal < total {
+        frames.push(AnimFrame {
+            image: render(reveal, true),
+            delay_ms: frame_ms,
+        });
+        reveal = (reveal + chars_per_frame).min(total);
+    }
+
+    // Hold on the finished code, blinking the cursor if requested, and
+    // always end on the cursor-free frame.
+    let mut hold_ms = typing.hold_ms;
+    if typing.cursor_blink {
+        while hold_ms >= BLINK_MS * 3 {
+            frames.push(AnimFrame {
+                image: render(total, true),
+                delay_ms: BLINK_MS,
+            });
+            frames.push(AnimFrame {
+                image: render(total, false),
+                delay_ms: BLINK_MS,
+            });
+            hold_ms -= BLINK_MS * 2;
+        }
+    }
+    frames.push(AnimFrame {
+        image: render(total, false),
+        delay_ms: hold_ms.max(MIN_FRAME_MS),
+    });
+    frames
+}
@@ -0,0 +1,389 @@
+use crate::render::Line;
+use syntect::highlighting::{Color, FontStyle, Style};
+
+/// Terminal colors used to resolve ANSI color codes.
+pub struct Palette {
+    pub fg: Color,
+    pub bg: Color,
+    /// The 16 standard colors: 0-7 normal, 8-15 bright.
+    pub colors: [Color; 16],
+}
+
+/// Palette names accepted by `--palette`.
+pub const PALETTE_NAMES: &[&str] = &[
+    "base16-ocean",
+    "xterm",
+    "vscode",
+    "tango",
+    "solarized-dark",
+    "dracula",
+];
+
+const fn hex(rgb: u32) -> Color {
+    Color {
+        r: (rgb >> 16) as u8,
+        g: (rgb >> 8) as u8,
+        b: rgb as u8,
+        a: 255,
+    }
+}
+
+pub fn palette(name: &str) -> Option<Palette> {
+    let (fg, bg, colors) = match name {
+        // Matches the default base16-ocean.dark syntax theme
+        "base16-ocean" => (
+            0xc0c5ce,
+            0x2b303b,
+            [
+                0x2b303b, 0xbf616a, 0xa3be8c, 0xebcb8b, 0x8fa1b3, 0xb48ead, 0x96b5b4, 0xc0c5ce,
+                0x65737e, 0xbf616a, 0xa3be8c, 0xebcb8b, 0x8fa1b3, 0xb48ead, 0x96b5b4, 0xeff1f5,
+            ],
+        ),
+        "xterm" => (
+            0xe5e5e5,
+            0x000000,
+            [
+                0x000000, 0xcd0000, 0x00cd00, 0xcdcd00, 0x0000ee, 0xcd00cd, 0x00cdcd, 0xe5e5e5,
+                0x7f7f7f, 0xff0000, 0x00ff00, 0xffff00, 0x5c5cff, 0xff00ff, 0x00ffff, 0xffffff,
+            ],
+        ),
+        "vscode" => (
+            0xcccccc,
+            0x1e1e1e,
+            [
+                0x000000, 0xcd3131, 0x0dbc79, 0xe5e510, 0x2472c8, 0xbc3fbc, 0x11a8cd, 0xe5e5e5,
+                0x666666, 0xf14c4c, 0x23d18b, 0xf5f543, 0x3b8eea, 0xd670d6, 0x29b8db, 0xe5e5e5,
+            ],
+        ),
+        "tango" => (
+            0xd3d7cf,
+            0x2e3436,
+            [
+                0x2e3436, 0xcc0000, 0x4e9a06, 0xc4a000, 0x3465a4, 0x75507b, 0x06989a, 0xd3d7cf,
+                0x555753, 0xef2929, 0x8ae234, 0xfce94f, 0x729fcf, 0xad7fa8, 0x34e2e2, 0xeeeeec,
+            ],
+        ),
+        "solarized-dark" => (
+            0x839496,
+            0x002b36,
+            [
+                0x073642, 0xdc322f, 0x859900, 0xb58900, 0x268bd2, 0xd33682, 0x2aa198, 0xeee8d5,
+                0x002b36, 0xcb4b16, 0x586e75, 0x657b83, 0x839496, 0x6c71c4, 0x93a1a1, 0xfdf6e3,
+            ],
+        ),
+        "dracula" => (
+            0xf8f8f2,
+            0x282a36,
+            [
+                0x21222c, 0xff5555, 0x50fa7b, 0xf1fa8c, 0xbd93f9, 0xff79c6, 0x8be9fd, 0xf8f8f2,
+                0x6272a4, 0xff6e6e, 0x69ff94, 0xffffa5, 0xd6acff, 0xff92df, 0xa4ffff, 0xffffff,
+            ],
+        ),
+        _ => return None,
+    };
+    Some(Palette {
+        fg: hex(fg),
+        bg: hex(bg),
+        colors: colors.map(hex),
+    })
+}
+
+impl Palette {
+    /// Resolve an entry of the xterm 256-color table.
+    fn indexed(&self, n: u8) -> Color {
+        match n {
+            0..=15 => self.colors[n as usize],
+            16..=231 => {
+                let n = n - 16;
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                Color {
+                    r: level(n / 36),
+                    g: level(n / 6 % 6),
+                    b: level(n % 6),
+                    a: 255,
+                }
+            }
+            232..=255 => {
+                let v = 8 + (n - 232) * 10;
+                Color {
+                    r: v,
+                    g: v,
+                    b: v,
+                    a: 255,
+                }
+            }
+        }
+    }
+}
+
+/// Current SGR attributes while scanning the input.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Pen {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    reverse: bool,
+}
+
+impl Pen {
+    /// No colors or attributes set.
+    pub const PLAIN: Pen = Pen {
+        fg: None,
+        bg: None,
+        bold: false,
+        dim: false,
+        italic: false,
+        underline: false,
+        reverse: false,
+    };
+
+    fn style(&self, palette: &Palette) -> Style {
+        let mut fg = self.fg.unwrap_or(palette.fg);
+        let mut bg = self.bg.unwrap_or(palette.bg);
+        if self.reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if self.dim {
+            let half = |f: u8, b: u8| ((f as u16 + b as u16) / 2) as u8;
+            fg = Color {
+                r: half(fg.r, bg.r),
+                g: half(fg.g, bg.g),
+                b: half(fg.b, bg.b),
+                a: 255,
+            };
+        }
+        // Only paint a background where the text asked for one; elsewhere the
+        // canvas shows through.
+        if self.bg.is_none() && !self.reverse {
+            bg.a = 0;
+        }
+        let mut font_style = FontStyle::empty();
+        font_style.set(FontStyle::BOLD, self.bold);
+        font_style.set(FontStyle::ITALIC, self.italic);
+        font_style.set(FontStyle::UNDERLINE, self.underline);
+        Style {
+            foreground: fg,
+            background: bg,
+            font_style,
+        }
+    }
+
+    /// Apply the parameters of one `ESC [ ... m` sequence.
+    pub fn apply_sgr(&mut self, params: &str, palette: &Palette) {
+        // An empty parameter list means reset
+        if params.is_empty() {
+            *self = Pen::default();
+            return;
+        }
+        // Parameters are separated by `;`; a parameter may carry `:`
+        // subparameters, as in the ITU form `38:2::r:g:b`
+        let mut params = params.split(';');
+        while let Some(param) = params.next() {
+            let mut subs = param.split(':').map(sgr_number);
+            let code = subs.next().unwrap_or(0);
+            let extended = |params: &mut std::str::Split<'_, char>| {
+                if param.contains(':') {
+                    let mut tail: Vec<u16> = param.split(':').skip(1).map(sgr_number).collect();
+                    // Drop the color space id that precedes r:g:b
+                    if tail.first() == Some(&2) && tail.len() > 4 {
+                        tail.remove(1);
+                    }
+                    extended_color(&mut tail.into_iter(), palette)
+                } else {
+                    extended_color(&mut params.map(sgr_number), palette)
+                }
+            };
+            match code {
+                0 => *self = Pen::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                // `4:0` turns underline off; `4:3` and friends are styles of it
+                4 => self.underline = subs.next() != Some(0),
+                7 => self.reverse = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                30..=37 => self.fg = Some(palette.colors[(code - 30) as usize]),
+                38 => self.fg = extended(&mut params).or(self.fg),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(palette.colors[(code - 40) as usize]),
+                48 => self.bg = extended(&mut params).or(self.bg),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(palette.colors[(code - 90 + 8) as usize]),
+                100..=107 => self.bg = Some(palette.colors[(code - 100 + 8) as usize]),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Value of one SGR parameter; empty or invalid ones count as 0.
+fn sgr_number(param: &str) -> u16 {
+    param.parse().unwrap_or(0)
+}
+
+/// Parse the `5;n` (256-color) or `2;r;g;b` (truecolor) tail of a 38/48 code.
+fn extended_color(codes: &mut impl Iterator<Item = u16>, palette: &Palette) -> Option<Color> {
+    match codes.next()? {
+        5 => Some(palette.indexed(codes.next()?.min(255) as u8)),
+        2 => {
+            let mut channel = || codes.next().map(|v| v.min(255) as u8);
+            Some(Color {
+                r: channel()?,
+                g: channel()?,
+                b: channel()?,
+                a: 255,
+            })
+        }
+        _ => None,
+    }
+}
+
+const TAB_WIDTH: usize = 8;
+
+/// Turn terminal output into styled lines. SGR color and attribute codes are
+/// honored; other escape sequences (cursor movement, titles, ...) are dropped.
+pub fn parse(input: &str, palette: &Palette) -> Vec<Line> {
+    let mut lines = Vec::new();
+    let mut line: Vec<(Pen, char)> = Vec::new();
+    let mut pen = Pen::default();
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters, intermediates, then a final byte
+                Some('[') => {
+                    let mut params = String::new();
+                    for c in chars.by_ref() {
+                        if ('\x40'..='\x7e').contains(&c) {
+                            if c == 'm' {
+                                pen.apply_sgr(&params, palette);
+                            }
+                            break;
+                        }
+                        params.push(c);
+                    }
+                }
+                // OSC: terminated by BEL or ESC \
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' || (c == '\x1b' && chars.next_if_eq(&'\\').is_some()) {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\n' => lines.push(std::mem::take(&mut line)),
+            // A bare carriage return redraws the line (progress bars)
+            '\r' if chars.peek() != Some(&'\n') => line.clear(),
+            '\x08' => {
+                line.pop();
+            }
+            '\t' => {
+                let spaces = TAB_WIDTH - line.len() % TAB_WIDTH;
+                line.extend(std::iter::repeat_n((pen, ' '), spaces));
+            }
+            c if c.is_control() => {}
+            c => line.push((pen, c)),
+        }
+    }
+    if !line.is_empty() {
+        lines.push(line);
+    }
+
+    lines
+        .into_iter()
+        .map(|cells| to_spans(&cells, palette))
+        .collect()
+}
+
+/// Merge runs of identically styled characters into spans.
+pub fn to_spans(cells: &[(Pen, char)], palette: &Palette) -> Line {
+    let mut spans: Line = Vec::new();
+    for (pen, c) in cells {
+        let style = pen.style(palette);
+        match spans.last_mut() {
+            Some((last, text)) if *last == style => text.push(*c),
+            _ => spans.push((style, c.to_string())),
+        }
+    }
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sgr(params: &str) -> Pen {
+        let mut pen = Pen::default();
+        pen.apply_sgr(params, &palette("xterm").unwrap());
+        pen
+    }
+
+    #[test]
+    fn basic_codes() {
+        let xterm = palette("xterm").unwrap();
+        let pen = sgr("1;3;4;31;44");
+        assert!(pen.bold && pen.italic && pen.underline);
+        assert_eq!(pen.fg, Some(xterm.colors[1]));
+        assert_eq!(pen.bg, Some(xterm.colors[4]));
+        assert_eq!(sgr("92;104").fg, Some(xterm.colors[10]));
+        assert_eq!(sgr("92;104").bg, Some(xterm.colors[12]));
+        assert!(!sgr("1;2;22").bold);
+        assert!(!sgr("4:0").underline);
+        assert!(sgr("4:3").underline);
+        assert!(sgr("1;31;0") == Pen::PLAIN);
+        assert!(sgr("31;39").fg.is_none());
+    }
+
+    #[test]
+    fn extended_colors() {
+        let xterm = palette("xterm").unwrap();
+        assert_eq!(sgr("38;5;196").fg, Some(hex(0xff0000)));
+        assert_eq!(sgr("38;5;110").fg, Some(hex(0x87afd7)));
+        assert_eq!(sgr("38;5;244").fg, Some(hex(0x808080)));
+        assert_eq!(sgr("48;5;3").bg, Some(xterm.colors[3]));
+        assert_eq!(sgr("38;2;10;20;30").fg, Some(hex(0x0a141e)));
+        // Truecolor parameters are consumed, so codes after them still apply
+        let pen = sgr("48;2;1;2;3;1");
+        assert_eq!(pen.bg, Some(hex(0x010203)));
+        assert!(pen.bold);
+    }
+
+    #[test]
+    fn colon_subparameters() {
+        assert_eq!(sgr("38:2::10:20:30").fg, Some(hex(0x0a141e)));
+        assert_eq!(sgr("38:2:0:10:20:30").fg, Some(hex(0x0a141e)));
+        assert_eq!(sgr("38:2:10:20:30").fg, Some(hex(0x0a141e)));
+        assert_eq!(sgr("48:5:196").bg, Some(hex(0xff0000)));
+        // Subparameters never spill into the next parameter
+        let pen = sgr("38:5;1");
+        assert!(pen.fg.is_none() && pen.bold);
+    }
+
+    #[test]
+    fn parse_lines() {
+        let xterm = palette("xterm").unwrap();
+        let text = |line: &Line| line.iter().map(|(_, t)| t.as_str()).collect::<String>();
+        let lines = parse(
+            "\x1b]0;title\x07\x1b[31mred\x1b[0m plain\n50%\r100%\na\tb\n",
+            &xterm,
+        );
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0][0].1, "red");
+        assert_eq!(lines[0][0].0.foreground, xterm.colors[1]);
+        assert_eq!(text(&lines[0]), "red plain");
+        assert_eq!(text(&lines[1]), "100%");
+        assert_eq!(text(&lines[2]), format!("a{}b", " ".repeat(7)));
+    }
+}
@@ -0,0 +1,478 @@
+use crate::anim::AnimFrame;
+use crate::ansi::{to_spans, Palette, Pen};
+use crate::render::{Line, Metrics};
+use image::{Rgb, RgbImage};
+use serde_json::Value;
+
+/// Shortest frame: events within this long of the last frame are merged
+/// into the next one.
+const MIN_FRAME_MS: u32 = 33;
+
+/// A parsed asciinema v2 recording.
+pub struct Recording {
+    pub cols: usize,
+    pub rows: usize,
+    /// Idle time limit stored in the header, if the recorder set one.
+    pub idle_time_limit: Option<f64>,
+    /// Output events as (seconds since start, data).
+    pub events: Vec<(f64, String)>,
+}
+
+/// Parse an asciinema v2 `.cast` file: a JSON header line followed by one
+/// `[time, type, data]` array per line. Only output (`"o"`) events are kept.
+pub fn parse(text: &str) -> Result<Recording, String> {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    let header: Value = lines
+        .next()
+        .ok_or("empty recording")
+        .and_then(|l| serde_json::from_str(l).map_err(|_| "invalid header"))?;
+    if header["version"] != 2 {
+        return Err("only asciinema v2 recordings are supported".into());
+    }
+    let dimension = |key: &str| {
+        header[key]
+            .as_u64()
+            .filter(|&n| n > 0)
+            .map(|n| n as usize)
+            .ok_or(format!("header is missing '{key}'"))
+    };
+
+    let mut events = Vec::new();
+    for (n, line) in lines.enumerate() {
+        let event: Value =
+            serde_json::from_str(line).map_err(|e| format!("event {}: {e}", n + 1))?;
+        let (Some(time), Some(kind), Some(data)) =
+            (event[0].as_f64(), event[1].as_str(), event[2].as_str())
+        else {
+            return Err(format!("event {}: expected [time, type, data]", n + 1));
+        };
+        if kind == "o" {
+            events.push((time, data.to_string()));
+        }
+    }
+
+    Ok(Recording {
+        cols: dimension("width")?,
+        rows: dimension("height")?,
+        idle_time_limit: header["idle_time_limit"].as_f64(),
+        events,
+    })
+}
+
+/// Replay `recording` and render a frame every `MIN_FRAME_MS` of output and
+/// whenever the screen settles, timed like the original with pauses capped
+/// at `idle_limit` seconds.
+pub fn cast_frames(
+    metrics: &Metrics,
+    recording: &Recording,
+    palette: &Palette,
+    idle_limit: f64,
+    hold_ms: u32,
+) -> Vec<AnimFrame> {
+    let width = (recording.cols as f32 * metrics.char_advance) as u32 + metrics.padding * 2;
+    let height = recording.rows as u32 * metrics.line_height + metrics.padding * 2;
+    let bg = Rgb([palette.bg.r, palette.bg.g, palette.bg.b]);
+    let fg = Rgb([palette.fg.r, palette.fg.g, palette.fg.b]);
+
+    let mut term = Terminal::new(recording.cols, recording.rows);
+    let mut frames: Vec<AnimFrame> = Vec::new();
+    // Playback clock with idle gaps already capped, in milliseconds
+    let mut clock = 0.0;
+    let mut last_time = 0.0;
+    let mut last_frame_at = 0.0;
+
+    let events = &recording.events;
+    for (i, (time, data)) in events.iter().enumerate() {
+        clock += (time - last_time).clamp(0.0, idle_limit) * 1000.0;
+        last_time = *time;
+        term.feed(data, palette);
+
+        // Render at most one frame per MIN_FRAME_MS of playback, so steady
+        // fast output still animates, plus one whenever the screen settles
+        let due = frames.is_empty() || clock - last_frame_at >= MIN_FRAME_MS as f64;
+        let settled = events
+            .get(i + 1)
+            .map(|(next, _)| (next - time).clamp(0.0, idle_limit) * 1000.0)
+            .is_none_or(|gap| gap >= MIN_FRAME_MS as f64);
+        if !due && !settled {
+            continue;
+        }
+
+        if let Some(prev) = frames.last_mut() {
+            prev.delay_ms = ((clock - last_frame_at) as u32).max(MIN_FRAME_MS);
+        } else if clock > 0.0 {
+            // Blank screen until the first output
+            frames.push(AnimFrame {
+                image: RgbImage::from_pixel(width, height, bg),
+                delay_ms: (clock as u32).max(MIN_FRAME_MS),
+            });
+        }
+        let mut img = RgbImage::from_pixel(width, height, bg);
+        metrics.draw_lines(&mut img, &term.lines(palette), None);
+        if term.cursor_visible {
+            let x = metrics.padding as f32 + term.x as f32 * metrics.char_advance;
+            metrics.draw_cursor(&mut img, (x as i32, metrics.line_y(term.y as f32)), fg);
+        }
+        frames.push(AnimFrame {
+            image: img,
+            delay_ms: MIN_FRAME_MS,
+        });
+        last_frame_at = clock;
+    }
+
+    match frames.last_mut() {
+        Some(last) => last.delay_ms = hold_ms.max(MIN_FRAME_MS),
+        None => frames.push(AnimFrame {
+            image: RgbImage::from_pixel(width, height, bg),
+            delay_ms: hold_ms.max(MIN_FRAME_MS),
+        }),
+    }
+    frames
+}
+
+/// Escape-sequence parser state, kept across events because recordings
+/// often split a sequence between two writes.
+enum State {
+    Ground,
+    Escape,
+    /// `ESC (` and friends: the next character picks a charset and is ignored.
+    Charset,
+    Csi(String),
+    Osc,
+    OscEscape,
+}
+
+type Cell = (Pen, char);
+
+const BLANK: Cell = (Pen::PLAIN, ' ');
+
+/// A minimal VT100-style screen: a fixed grid, a cursor, SGR attributes,
+/// cursor movement, erasing, scrolling and auto-wrap.
+struct Terminal {
+    cols: usize,
+    rows: usize,
+    grid: Vec<Vec<Cell>>,
+    x: usize,
+    y: usize,
+    saved: (usize, usize),
+    /// Set after writing the last column; the next character wraps first.
+    wrap_pending: bool,
+    cursor_visible: bool,
+    pen: Pen,
+    state: State,
+}
+
+impl Terminal {
+    fn new(cols: usize, rows: usize) -> Self {
+        Terminal {
+            cols,
+            rows,
+            grid: vec![vec![BLANK; cols]; rows],
+            x: 0,
+            y: 0,
+            saved: (0, 0),
+            wrap_pending: false,
+            cursor_visible: true,
+            pen: Pen::default(),
+            state: State::Ground,
+        }
+    }
+
+    /// Screen contents as styled lines, without trailing blank cells.
+    fn lines(&self, palette: &Palette) -> Vec<Line> {
+        self.grid
+            .iter()
+            .map(|row| {
+                let end = row.iter().rposition(|c| *c != BLANK).map_or(0, |i| i + 1);
+                to_spans(&row[..end], palette)
+            })
+            .collect()
+    }
+
+    fn feed(&mut self, data: &str, palette: &Palette) {
+        for c in data.chars() {
+            match std::mem::replace(&mut self.state, State::Ground) {
+                State::Ground => self.ground(c),
+                State::Escape => self.escape(c),
+                State::Charset => {}
+                State::Csi(mut params) => {
+                    if ('\x40'..='\x7e').contains(&c) {
+                        self.csi(&params, c, palette);
+                    } else {
+                        params.push(c);
+                        self.state = State::Csi(params);
+                    }
+                }
+                State::Osc => {
+                    self.state = match c {
+                        '\x07' => State::Ground,
+                        '\x1b' => State::OscEscape,
+                        _ => State::Osc,
+                    }
+                }
+                State::OscEscape => {
+                    if c != '\\' {
+                        self.state = State::Osc;
+                    }
+                }
+            }
+        }
+    }
+
+    fn ground(&mut self, c: char) {
+        match c {
+            '\x1b' => self.state = State::Escape,
+            '\r' => self.carriage_return(),
+            '\n' | '\x0b' | '\x0c' => self.line_feed(),
+            '\x08' => {
+                self.x = self.x.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            '\t' => self.x = ((self.x / 8 + 1) * 8).min(self.cols - 1),
+            c if c.is_control() => {}
+            c => self.print(c),
+        }
+    }
+
+    fn escape(&mut self, c: char) {
+        match c {
+            '[' => self.state = State::Csi(String::new()),
+            ']' => self.state = State::Osc,
+            '(' | ')' | '*' | '+' => self.state = State::Charset,
+            '7' => self.saved = (self.x, self.y),
+            '8' => (self.x, self.y) = self.saved,
+            'D' => self.line_feed(),
+            'E' => {
+                self.carriage_return();
+                self.line_feed();
+            }
+            'M' => {
+                if self.y == 0 {
+                    self.grid.pop();
+                    self.grid.insert(0, vec![BLANK; self.cols]);
+                } else {
+                    self.y -= 1;
+                }
+            }
+            'c' => *self = Terminal::new(self.cols, self.rows),
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: char, palette: &Palette) {
+        if let Some(private) = params.strip_prefix('?') {
+            match (private, action) {
+                ("25", 'h') => self.cursor_visible = true,
+                ("25", 'l') => self.cursor_visible = false,
+                // Entering or leaving the alternate screen starts from a blank one
+                ("1049" | "47" | "1047", 'h' | 'l') => {
+                    self.erase_rows(0..self.rows);
+                    (self.x, self.y) = (0, 0);
+                }
+                _ => {}
+            }
+            return;
+        }
+        if action == 'm' {
+            self.pen.apply_sgr(params, palette);
+            return;
+        }
+
+        let args: Vec<usize> = params.split(';').map(|p| p.parse().unwrap_or(0)).collect();
+        let arg = |i: usize, default: usize| match args.get(i) {
+            Some(&0) | None => default,
+            Some(&n) => n,
+        };
+        let n = arg(0, 1);
+        self.wrap_pending = false;
+
+        match action {
+            'A' => self.y = self.y.saturating_sub(n),
+            'B' | 'e' => self.y = (self.y + n).min(self.rows - 1),
+            'C' | 'a' => self.x = (self.x + n).min(self.cols - 1),
+            'D' => self.x = self.x.saturating_sub(n),
+            'E' => (self.x, self.y) = (0, (self.y + n).min(self.rows - 1)),
+            'F' => (self.x, self.y) = (0, self.y.saturating_sub(n)),
+            'G' | '`' => self.x = (n - 1).min(self.cols - 1),
+            'd' => self.y = (n - 1).min(self.rows - 1),
+            'H' | 'f' => {
+                self.y = (arg(0, 1) - 1).min(self.rows - 1);
+                self.x = (arg(1, 1) - 1).min(self.cols - 1);
+            }
+            'J' => match args.first().copied().unwrap_or(0) {
+                0 => {
+                    self.erase_cells(self.x..self.cols);
+                    self.erase_rows(self.y + 1..self.rows);
+                }
+                1 => {
+                    self.erase_rows(0..self.y);
+                    self.erase_cells(0..self.x + 1);
+                }
+                _ => self.erase_rows(0..self.rows),
+            },
+            'K' => match args.first().copied().unwrap_or(0) {
+                0 => self.erase_cells(self.x..self.cols),
+                1 => self.erase_cells(0..self.x + 1),
+                _ => self.erase_cells(0..self.cols),
+            },
+            'X' => self.erase_cells(self.x..(self.x + n).min(self.cols)),
+            'P' => {
+                let row = &mut self.grid[self.y];
+                let n = n.min(self.cols - self.x);
+                row.drain(self.x..self.x + n);
+                row.extend(std::iter::repeat_n(BLANK, n));
+            }
+            '@' => {
+                let row = &mut self.grid[self.y];
+                let n = n.min(self.cols - self.x);
+                row.truncate(self.cols - n);
+                row.splice(self.x..self.x, std::iter::repeat_n(BLANK, n));
+            }
+            'L' => {
+                for _ in 0..n.min(self.rows - self.y) {
+                    self.grid.pop();
+                    self.grid.insert(self.y, vec![BLANK; self.cols]);
+                }
+            }
+            'M' => {
+                for _ in 0..n.min(self.rows - self.y) {
+                    self.grid.remove(self.y);
+                    self.grid.push(vec![BLANK; self.cols]);
+                }
+            }
+            'S' => {
+                for _ in 0..n.min(self.rows) {
+                    self.scroll_up();
+                }
+            }
+            's' => self.saved = (self.x, self.y),
+            'u' => (self.x, self.y) = self.saved,
+            _ => {}
+        }
+    }
+
+    fn print(&mut self, c: char) {
+        if self.wrap_pending {
+            self.carriage_return();
+            self.line_feed();
+        }
+        self.grid[self.y][self.x] = (self.pen, c);
+        if self.x + 1 == self.cols {
+            self.wrap_pending = true;
+        } else {
+            self.x += 1;
+        }
+    }
+
+    fn carriage_return(&mut self) {
+        self.x = 0;
+        self.wrap_pending = false;
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.y + 1 == self.rows {
+            self.scroll_up();
+        } else {
+            self.y += 1;
+        }
+    }
+
+    fn scroll_up(&mut self) {
+        self.grid.remove(0);
+        self.grid.push(vec![BLANK; self.cols]);
+    }
+
+    fn erase_cells(&mut self, range: std::ops::Range<usize>) {
+        self.grid[self.y][range].fill(BLANK);
+    }
+
+    fn erase_rows(&mut self, range: std::ops::Range<usize>) {
+        for row in &mut self.grid[range] {
+            row.fill(BLANK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ansi::palette;
+
+    /// Screen text after feeding `data` to a `cols` x `rows` terminal, one
+    /// string per row with trailing blanks dropped.
+    fn screen(cols: usize, rows: usize, data: &[&str]) -> (Vec<String>, (usize, usize)) {
+        let palette = palette("xterm").unwrap();
+        let mut term = Terminal::new(cols, rows);
+        for chunk in data {
+            term.feed(chunk, &palette);
+        }
+        let text = term
+            .lines(&palette)
+            .iter()
+            .map(|line| line.iter().map(|(_, t)| t.as_str()).collect())
+            .collect();
+        (text, (term.x, term.y))
+    }
+
+    #[test]
+    fn cursor_movement() {
+        let (text, cursor) = screen(10, 3, &["abc\x1b[2;5Hx\x1b[Ay\x1b[3Dz\x1b[B\x1b[2Cw"]);
+        assert_eq!(text, ["abcz y", "    x w", ""]);
+        assert_eq!(cursor, (7, 1));
+        // Moves stop at the edges of the screen
+        let (_, cursor) = screen(10, 3, &["\x1b[99;99H"]);
+        assert_eq!(cursor, (9, 2));
+        let (_, cursor) = screen(10, 3, &["\x1b[5A\x1b[5D"]);
+        assert_eq!(cursor, (0, 0));
+        let (text, _) = screen(10, 3, &["ab\x1b7\r\ncd\x1b8e"]);
+        assert_eq!(text, ["abe", "cd", ""]);
+    }
+
+    #[test]
+    fn erase() {
+        let rows = ["0123456789\r\n", "0123456789\r\n", "0123456789"];
+        let at_middle = |command: &str| {
+            let data = [rows[0], rows[1], rows[2], "\x1b[2;5H", command];
+            screen(10, 3, &data).0
+        };
+        assert_eq!(at_middle("\x1b[K"), ["0123456789", "0123", "0123456789"]);
+        assert_eq!(
+            at_middle("\x1b[1K"),
+            ["0123456789", "     56789", "0123456789"]
+        );
+        assert_eq!(at_middle("\x1b[2K"), ["0123456789", "", "0123456789"]);
+        assert_eq!(at_middle("\x1b[J"), ["0123456789", "0123", ""]);
+        assert_eq!(at_middle("\x1b[1J"), ["", "     56789", "0123456789"]);
+        assert_eq!(at_middle("\x1b[2J"), ["", "", ""]);
+        assert_eq!(
+            at_middle("\x1b[2X"),
+            ["0123456789", "0123  6789", "0123456789"]
+        );
+        assert_eq!(
+            at_middle("\x1b[2P"),
+            ["0123456789", "01236789", "0123456789"]
+        );
+    }
+
+    #[test]
+    fn wrap_and_scroll() {
+        // Writing the last column defers the wrap until the next character
+        let (text, cursor) = screen(4, 2, &["abcd"]);
+        assert_eq!(text, ["abcd", ""]);
+        assert_eq!(cursor, (3, 0));
+        let (text, _) = screen(4, 2, &["abcdef"]);
+        assert_eq!(text, ["abcd", "ef"]);
+        let (text, _) = screen(4, 2, &["abcd\r\nx"]);
+        assert_eq!(text, ["abcd", "x"]);
+        let (text, cursor) = screen(4, 2, &["abcdefghij"]);
+        assert_eq!(text, ["efgh", "ij"]);
+        assert_eq!(cursor, (2, 1));
+    }
+
+    #[test]
+    fn sequences_split_across_events() {
+        let (text, _) = screen(10, 2, &["a\x1b[", "31mb\x1b]0;ti", "tle\x07c"]);
+        assert_eq!(text, ["abc", ""]);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::render::{blend, plain_style, Line, Metrics};
+use clap::ValueEnum;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+
+/// How the two columns of a comparison are separated.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Divider {
+    /// A solid vertical rule
+    Line,
+    /// A dashed vertical rule
+    Dashed,
+    /// Only the gap between the columns
+    None,
+}
+
+/// Render `columns` next to each other on one shared background, aligned to
+/// the top and padded to the height of the tallest. Each column may carry a
+/// header shown above its code with a rule underneath.
+pub fn side_by_side(
+    metrics: &Metrics,
+    columns: &[Vec<Line>],
+    titles: &[String],
+    bg: Rgb<u8>,
+    fg: Rgb<u8>,
+    divider: Divider,
+) -> RgbImage {
+    let rule = blend(fg, bg, 0.25);
+    let has_titles = !titles.is_empty();
+
+    // A header takes one row, plus an empty row for the rule beneath it
+    let columns: Vec<Vec<Line>> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, lines)| {
+            let mut column = Vec::new();
+            if has_titles {
+                let title = titles.get(i).cloned().unwrap_or_default();
+                column.push(vec![(plain_style(fg, true), title)]);
+                column.push(Vec::new());
+            }
+            column.extend(lines.iter().cloned());
+            column
+        })
+        .collect();
+
+    let sizes: Vec<(u32, u32)> = columns.iter().map(|c| metrics.canvas_size(c)).collect();
+    let width = sizes.iter().map(|s| s.0).sum();
+    let height = sizes.iter().map(|s| s.1).max().unwrap_or(0);
+    let mut img = RgbImage::from_pixel(width, height, bg);
+
+    let mut x = 0;
+    for (i, (column, (column_width, _))) in columns.iter().zip(&sizes).enumerate() {
+        let mut panel = RgbImage::from_pixel(*column_width, height, bg);
+        metrics.draw_lines(&mut panel, column, None);
+        if has_titles {
+            let y = metrics.line_y(1.5) as u32;
+            let inset = metrics.padding / 2;
+            let rect = Rect::at(inset as i32, y as i32).of_size(column_width - inset * 2, 1);
+            draw_filled_rect_mut(&mut panel, rect, rule);
+        }
+        image::imageops::replace(&mut img, &panel, x as i64, 0);
+
+        if i > 0 {
+            draw_divider(&mut img, x, metrics.padding / 2, height, rule, divider);
+        }
+        x += column_width;
+    }
+    img
+}
+
+fn draw_divider(
+    img: &mut RgbImage,
+    x: u32,
+    inset: u32,
+    height: u32,
+    color: Rgb<u8>,
+    divider: Divider,
+) {
+    let dash = match divider {
+        Divider::Line => height,
+        Divider::Dashed => 8,
+        Divider::None => return,
+    };
+    let mut y = inset;
+    while y < height - inset {
+        let len = dash.min(height - inset - y);
+        draw_filled_rect_mut(img, Rect::at(x as i32, y as i32).of_size(1, len), color);
+        y += dash * 2;
+    }
+}
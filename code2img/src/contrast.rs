@@ -0,0 +1,267 @@
+use crate::render::background;
+use image::Rgb;
+use syntect::highlighting::{Color, Theme};
+
+/// Minimum ratio WCAG 2 level AA requires for normal text.
+pub const AA: f64 = 4.5;
+
+/// A theme color and the scopes it is used for.
+pub struct Entry {
+    pub scope: String,
+    pub color: Rgb<u8>,
+    pub ratio: f64,
+}
+
+/// WCAG relative luminance of an sRGB color.
+fn luminance(color: Rgb<u8>) -> f64 {
+    let channel = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    0.2126 * channel(color[0]) + 0.7152 * channel(color[1]) + 0.0722 * channel(color[2])
+}
+
+/// WCAG contrast ratio between two colors, from 1 to 21.
+pub fn ratio(a: Rgb<u8>, b: Rgb<u8>) -> f64 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+fn rgb(c: Color) -> Rgb<u8> {
+    Rgb([c.r, c.g, c.b])
+}
+
+/// Every foreground color `theme` defines, labeled with its scope selectors,
+/// along with its contrast against the theme background.
+pub fn audit(theme: &Theme) -> Vec<Entry> {
+    let bg = background(theme);
+    let mut entries = Vec::new();
+    if let Some(fg) = theme.settings.foreground {
+        entries.push(Entry {
+            scope: "(default foreground)".to_string(),
+            color: rgb(fg),
+            ratio: ratio(rgb(fg), bg),
+        });
+    }
+    for item in &theme.scopes {
+        let Some(fg) = item.style.foreground else {
+            continue;
+        };
+        let scope = item
+            .scope
+            .selectors
+            .iter()
+            .map(|s| {
+                let mut text = s.path.to_string().trim_end().to_string();
+                for exclude in &s.excludes {
+                    text.push_str(&format!(" - {}", exclude.to_string().trim_end()));
+                }
+                text
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        entries.push(Entry {
+            scope,
+            color: rgb(fg),
+            ratio: ratio(rgb(fg), bg),
+        });
+    }
+    entries
+}
+
+fn to_hsl(c: Rgb<u8>) -> (f64, f64, f64) {
+    let [r, g, b] = c.0.map(|v| v as f64 / 255.0);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let l = (max + min) / 2.0;
+    if max == min {
+        return (0.0, 0.0, l);
+    }
+    let d = max - min;
+    let s = if l > 0.5 {
+        d / (2.0 - max - min)
+    } else {
+        d / (max + min)
+    };
+    let h = if max == r {
+        (g - b) / d + if g < b { 6.0 } else { 0.0 }
+    } else if max == g {
+        (b - r) / d + 2.0
+    } else {
+        (r - g) / d + 4.0
+    };
+    (h / 6.0, s, l)
+}
+
+fn from_hsl((h, s, l): (f64, f64, f64)) -> Rgb<u8> {
+    let channel = |t: f64| {
+        let q = if l < 0.5 {
+            l * (1.0 + s)
+        } else {
+            l + s - l * s
+        };
+        let p = 2.0 * l - q;
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+    Rgb([channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0)])
+}
+
+/// `color` with its lightness moved just far enough from `bg` to reach
+/// `min_ratio`, keeping hue and saturation. Moves toward white on dark
+/// backgrounds and toward black on light ones, trying the other direction
+/// only if the first cannot get there.
+pub fn adjust(color: Rgb<u8>, bg: Rgb<u8>, min_ratio: f64) -> Rgb<u8> {
+    if ratio(color, bg) >= min_ratio {
+        return color;
+    }
+    let (h, s, l) = to_hsl(color);
+    let lighter_first = luminance(bg) < 0.5;
+    for lighter in [lighter_first, !lighter_first] {
+        let limit = if lighter { 1.0 } else { 0.0 };
+        if ratio(from_hsl((h, s, limit)), bg) < min_ratio {
+            continue;
+        }
+        // Smallest lightness change that still reaches the ratio
+        let (mut near, mut far) = (l, limit);
+        for _ in 0..24 {
+            let mid = (near + far) / 2.0;
+            if ratio(from_hsl((h, s, mid)), bg) >= min_ratio {
+                far = mid;
+            } else {
+                near = mid;
+            }
+        }
+        return from_hsl((h, s, far));
+    }
+    color
+}
+
+/// Raise every foreground color in `theme` to at least `min_ratio` against
+/// the theme background.
+pub fn adjust_theme(theme: &mut Theme, min_ratio: f64) {
+    let bg = background(theme);
+    let fix = |c: &mut Color| {
+        let Rgb([r, g, b]) = adjust(rgb(*c), bg, min_ratio);
+        (c.r, c.g, c.b) = (r, g, b);
+    };
+    if let Some(fg) = theme.settings.foreground.as_mut() {
+        fix(fg);
+    }
+    for item in &mut theme.scopes {
+        if let Some(fg) = item.style.foreground.as_mut() {
+            fix(fg);
+        }
+    }
+}
+
+/// Human-readable list of the colors in `theme` below `min_ratio`, with
+/// the replacement color when `adjusting`.
+pub fn report(theme: &Theme, min_ratio: f64, adjusting: bool) -> String {
+    let bg = background(theme);
+    let hex = |c: Rgb<u8>| format!("#{:02x}{:02x}{:02x}", c[0], c[1], c[2]);
+    let entries = audit(theme);
+    let failing: Vec<&Entry> = entries.iter().filter(|e| e.ratio < min_ratio).collect();
+
+    let mut out = format!(
+        "Contrast against background {} (minimum {:.1}:1): {} of {} colors fail\n",
+        hex(bg),
+        min_ratio,
+        failing.len(),
+        entries.len()
+    );
+    for entry in failing {
+        out.push_str(&format!(
+            "  {:.2}:1  {}  {}",
+            entry.ratio,
+            hex(entry.color),
+            entry.scope
+        ));
+        if adjusting {
+            let fixed = adjust(entry.color, bg, min_ratio);
+            out.push_str(&format!("  -> {} ({:.2}:1)", hex(fixed), ratio(fixed, bg)));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DARK: Rgb<u8> = Rgb([0x2b, 0x30, 0x3b]);
+    const LIGHT: Rgb<u8> = Rgb([0xff, 0xff, 0xff]);
+
+    #[test]
+    fn wcag_ratios() {
+        assert!((ratio(Rgb([0, 0, 0]), LIGHT) - 21.0).abs() < 1e-9);
+        assert!((ratio(DARK, DARK) - 1.0).abs() < 1e-9);
+        assert_eq!(ratio(DARK, LIGHT), ratio(LIGHT, DARK));
+        // #767676 is the lightest gray that passes AA on white
+        assert!(ratio(Rgb([0x76, 0x76, 0x76]), LIGHT) >= AA);
+        assert!(ratio(Rgb([0x77, 0x77, 0x77]), LIGHT) < AA);
+    }
+
+    #[test]
+    fn hsl_round_trip() {
+        for color in [
+            [0x65, 0x73, 0x7e],
+            [0xbf, 0x61, 0x6a],
+            [0, 0, 0],
+            [0xff, 0xff, 0xff],
+        ] {
+            assert_eq!(from_hsl(to_hsl(Rgb(color))), Rgb(color));
+        }
+    }
+
+    #[test]
+    fn passing_colors_are_kept() {
+        let color = Rgb([0xc0, 0xc5, 0xce]);
+        assert_eq!(adjust(color, DARK, AA), color);
+    }
+
+    #[test]
+    fn lightens_on_dark_backgrounds() {
+        let comment = Rgb([0x65, 0x73, 0x7e]);
+        let fixed = adjust(comment, DARK, AA);
+        let ratio = ratio(fixed, DARK);
+        // Just far enough: rounding to 8 bits leaves a little headroom
+        assert!((AA..AA + 0.1).contains(&ratio), "{}", ratio);
+        assert!(luminance(fixed) > luminance(comment));
+        let ((h0, s0, _), (h1, s1, _)) = (to_hsl(comment), to_hsl(fixed));
+        assert!((h0 - h1).abs() < 0.01 && (s0 - s1).abs() < 0.05);
+    }
+
+    #[test]
+    fn darkens_on_light_backgrounds() {
+        let yellow = Rgb([0xeb, 0xcb, 0x8b]);
+        let fixed = adjust(yellow, LIGHT, AA);
+        assert!(ratio(fixed, LIGHT) >= AA);
+        assert!(luminance(fixed) < luminance(yellow));
+    }
+
+    #[test]
+    fn falls_back_to_the_other_direction() {
+        // White can't reach 4.58:1 on this gray, but black can
+        let gray = Rgb([0x76, 0x76, 0x76]);
+        let color = Rgb([0x90, 0x60, 0x60]);
+        let fixed = adjust(color, gray, 4.58);
+        assert!(ratio(fixed, gray) >= 4.58);
+        assert!(luminance(fixed) < luminance(color));
+        // Unreachable ratios leave the color alone
+        assert_eq!(adjust(color, gray, 10.0), color);
+    }
+}
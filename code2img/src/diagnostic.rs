@@ -0,0 +1,643 @@
+use crate::marks::MarkKind;
+use crate::render::{blend, line_chars, plain_style, Line};
+use image::Rgb;
+use serde_json::Value;
+use std::path::{Component, Path};
+
+/// A compiler diagnostic reduced to what the annotated snippet needs.
+pub struct Diagnostic {
+    /// `error`, `warning`, `note`, ...
+    pub level: String,
+    pub code: Option<String>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    /// Span-less sub-diagnostics shown as `= level: message`.
+    pub notes: Vec<(String, String)>,
+}
+
+/// A labeled source span, converted to 0-based line and character columns.
+pub struct Label {
+    pub line: usize,
+    pub start: usize,
+    pub end_line: usize,
+    pub end: usize,
+    pub primary: bool,
+    pub text: String,
+}
+
+/// Character positions for byte offsets and 1-based line/column pairs in
+/// the source a diagnostic refers to.
+struct SourceIndex<'a> {
+    lines: Vec<&'a str>,
+    /// Byte offset where each line starts.
+    starts: Vec<usize>,
+}
+
+impl<'a> SourceIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let lines: Vec<&str> = source.split_inclusive('\n').collect();
+        let starts = lines
+            .iter()
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some(start)
+            })
+            .collect();
+        SourceIndex { lines, starts }
+    }
+
+    /// (line, char column) of a byte offset into the source.
+    fn position(&self, byte: usize) -> Option<(usize, usize)> {
+        let line = self.starts.partition_point(|&s| s <= byte).checked_sub(1)?;
+        let text = self.lines[line];
+        let within = byte - self.starts[line];
+        let within = (0..=within).rev().find(|&b| text.is_char_boundary(b))?;
+        Some((line, text[..within].chars().count()))
+    }
+
+    fn span(&self, start: usize, end: usize) -> Option<(usize, usize, usize, usize)> {
+        let total = self
+            .starts
+            .last()
+            .zip(self.lines.last())
+            .map_or(0, |(s, l)| s + l.len());
+        if start > end || end > total {
+            return None;
+        }
+        let (line, col) = self.position(start)?;
+        let (end_line, end_col) = self.position(end)?;
+        Some((line, col, end_line, end_col))
+    }
+}
+
+/// Whether a span's `file` (a path, or a SARIF `file://` URI) names the
+/// input at `path`: one must end with the other's components, as
+/// `src/main.rs` and `/work/app/src/main.rs` do. Input read from stdin
+/// (`-`) has no name to compare, so every file matches it.
+fn same_file(file: &str, path: &str) -> bool {
+    if path == "-" {
+        return true;
+    }
+    let names = |p: &str| -> Vec<String> {
+        Path::new(p)
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect()
+    };
+    let file = names(file.strip_prefix("file://").unwrap_or(file));
+    let path = names(path);
+    let n = file.len().min(path.len());
+    n > 0 && file[file.len() - n..] == path[path.len() - n..]
+}
+
+/// Parse rustc `--error-format=json` output (bare or wrapped in cargo's
+/// `compiler-message` records, one JSON object per line) or a SARIF log
+/// about the input file at `path`. Spans in other files or outside `source`
+/// are dropped, as are diagnostics left without any.
+pub fn parse(json: &str, source: &str, path: &str) -> Result<Vec<Diagnostic>, String> {
+    let index = SourceIndex::new(source);
+    let mut diagnostics = match serde_json::from_str::<Value>(json) {
+        Ok(log) if log.get("runs").is_some() => parse_sarif(&log, &index, path),
+        _ => parse_rustc_lines(json, &index, path)?,
+    };
+    // Summaries such as "aborting due to 1 previous error" point at no code
+    diagnostics.retain(|d| !d.labels.is_empty());
+    if diagnostics.is_empty() {
+        return Err("no diagnostics found".into());
+    }
+    Ok(diagnostics)
+}
+
+/// Diagnostics from rustc or cargo JSON, one object per line.
+fn parse_rustc_lines(
+    json: &str,
+    index: &SourceIndex,
+    path: &str,
+) -> Result<Vec<Diagnostic>, String> {
+    let mut diagnostics = Vec::new();
+    for (n, line) in json.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let value: Value =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", n + 1, e))?;
+        // cargo wraps each diagnostic as {"reason": "compiler-message", "message": {...}}
+        let message = match value.get("reason") {
+            Some(reason) if reason == "compiler-message" => &value["message"],
+            Some(_) => continue,
+            None => &value,
+        };
+        if message["message"].is_string() {
+            diagnostics.push(parse_rustc(message, index, path));
+        }
+    }
+    Ok(diagnostics)
+}
+
+fn parse_rustc(diag: &Value, index: &SourceIndex, path: &str) -> Diagnostic {
+    let labels = diag["spans"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|span| {
+            span["file_name"]
+                .as_str()
+                .is_none_or(|f| same_file(f, path))
+        })
+        .filter_map(|span| {
+            let start = span["byte_start"].as_u64()? as usize;
+            let end = span["byte_end"].as_u64()? as usize;
+            let (line, start, end_line, end) = index.span(start, end)?;
+            Some(Label {
+                line,
+                start,
+                end_line,
+                end,
+                primary: span["is_primary"].as_bool().unwrap_or(false),
+                text: span["label"].as_str().unwrap_or_default().to_string(),
+            })
+        })
+        .collect();
+    let notes = diag["children"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|child| {
+            (
+                child["level"].as_str().unwrap_or("note").to_string(),
+                child["message"].as_str().unwrap_or_default().to_string(),
+            )
+        })
+        .collect();
+    Diagnostic {
+        level: diag["level"].as_str().unwrap_or("error").to_string(),
+        code: diag["code"]["code"].as_str().map(str::to_string),
+        message: diag["message"].as_str().unwrap_or_default().to_string(),
+        labels,
+        notes,
+    }
+}
+
+fn parse_sarif(log: &Value, index: &SourceIndex, path: &str) -> Vec<Diagnostic> {
+    let results = log["runs"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .flat_map(|run| run["results"].as_array().into_iter().flatten());
+
+    let label = |location: &Value, primary: bool| -> Option<Label> {
+        let physical = &location["physicalLocation"];
+        let uri = physical["artifactLocation"]["uri"].as_str();
+        if !uri.is_none_or(|uri| same_file(uri, path)) {
+            return None;
+        }
+        let region = &physical["region"];
+        let (line, start, end_line, end) = match region["byteOffset"].as_u64() {
+            Some(offset) => {
+                let length = region["byteLength"].as_u64().unwrap_or(0);
+                index.span(offset as usize, (offset + length) as usize)?
+            }
+            None => {
+                let line = region["startLine"].as_u64()?.checked_sub(1)? as usize;
+                let start = region["startColumn"]
+                    .as_u64()
+                    .unwrap_or(1)
+                    .saturating_sub(1) as usize;
+                let end_line = region["endLine"]
+                    .as_u64()
+                    .map_or(line, |l| l.saturating_sub(1) as usize);
+                let end = region["endColumn"]
+                    .as_u64()
+                    .map_or(start + 1, |c| c.saturating_sub(1) as usize);
+                (line, start, end_line, end)
+            }
+        };
+        (line < index.lines.len()).then(|| Label {
+            line,
+            start,
+            end_line,
+            end,
+            primary,
+            text: location["message"]["text"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+        })
+    };
+
+    results
+        .map(|result| {
+            let primary = result["locations"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|l| label(l, true));
+            let related = result["relatedLocations"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|l| label(l, false));
+            Diagnostic {
+                level: match result["level"].as_str() {
+                    Some("note") | Some("none") => "note".to_string(),
+                    Some(level) => level.to_string(),
+                    None => "warning".to_string(),
+                },
+                code: result["ruleId"].as_str().map(str::to_string),
+                message: result["message"]["text"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                labels: primary.chain(related).collect(),
+                notes: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn level_color(level: &str) -> Rgb<u8> {
+    match level {
+        "error" | "error: internal compiler error" => MarkKind::Error.color(),
+        "warning" => MarkKind::Warning.color(),
+        _ => MarkKind::Info.color(),
+    }
+}
+
+/// An underline under one line of source: character range, whether it
+/// belongs to a primary span, and the label to print (if this is the span's
+/// last line).
+struct Underline<'a> {
+    start: usize,
+    end: usize,
+    primary: bool,
+    text: &'a str,
+}
+
+/// Source lines shown around each labeled line.
+const CONTEXT_LINES: usize = 1;
+
+/// Indices of the source lines to show for `labels`: the first and last
+/// line of each span with `CONTEXT_LINES` around them, within `count`.
+fn visible_lines(labels: &[Label], count: usize) -> Vec<usize> {
+    let mut lines: Vec<usize> = labels
+        .iter()
+        .flat_map(|l| [l.line, l.end_line])
+        .flat_map(|line| line.saturating_sub(CONTEXT_LINES)..=line + CONTEXT_LINES)
+        .filter(|&line| line < count)
+        .collect();
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
+
+/// Lay out `diagnostics` the way rustc prints them: a header, a `--> file`
+/// location, a line-number gutter, the labeled lines with a little context
+/// (`...` marks the lines left out), and `^^^`/`---` underlines with labels
+/// below the lines their spans cover.
+pub fn annotate(
+    file: &str,
+    source: &[Line],
+    diagnostics: &[Diagnostic],
+    fg: Rgb<u8>,
+    bg: Rgb<u8>,
+) -> Vec<Line> {
+    let gutter_color = MarkKind::Info.color();
+    let width = source.len().to_string().len();
+    let gutter = |number: Option<usize>| {
+        let number = number.map_or(String::new(), |n| n.to_string());
+        (
+            plain_style(gutter_color, true),
+            format!("{:>width$} | ", number),
+        )
+    };
+    let text_style = plain_style(fg, true);
+
+    let mut out: Vec<Line> = Vec::new();
+    for (i, diag) in diagnostics.iter().enumerate() {
+        if i > 0 {
+            out.push(Vec::new());
+        }
+        let level_style = plain_style(level_color(&diag.level), true);
+        let header = match &diag.code {
+            Some(code) => format!("{}[{}]", diag.level, code),
+            None => diag.level.clone(),
+        };
+        out.push(vec![
+            (level_style, header),
+            (text_style, format!(": {}", diag.message)),
+        ]);
+        if let Some(primary) = diag
+            .labels
+            .iter()
+            .find(|l| l.primary)
+            .or(diag.labels.first())
+        {
+            out.push(vec![(
+                plain_style(gutter_color, true),
+                format!(
+                    "{:>width$}--> {}:{}:{}",
+                    "",
+                    file,
+                    primary.line + 1,
+                    primary.start + 1
+                ),
+            )]);
+        }
+        out.push(vec![gutter(None)]);
+
+        let mut previous: Option<usize> = None;
+        for line_idx in visible_lines(&diag.labels, source.len()) {
+            // A single hidden line takes no more room than `...`, so show it
+            let gap = previous.map_or(0, |p| line_idx - p - 1);
+            let shown = match gap {
+                0 => line_idx..=line_idx,
+                1 => line_idx - 1..=line_idx,
+                _ => {
+                    out.push(vec![(plain_style(gutter_color, true), "...".to_string())]);
+                    line_idx..=line_idx
+                }
+            };
+            previous = Some(line_idx);
+            for line_idx in shown {
+                annotate_line(&mut out, &source[line_idx], line_idx, diag, &gutter, fg, bg);
+            }
+        }
+
+        for (level, message) in &diag.notes {
+            out.push(vec![
+                gutter(None),
+                (plain_style(fg, true), format!("= {}: ", level)),
+                (plain_style(blend(fg, bg, 0.8), false), message.clone()),
+            ]);
+        }
+    }
+    out
+}
+
+/// Append source line `line_idx` with its gutter number to `out`, followed
+/// by the underline rows for the labels of `diag` that cover it.
+fn annotate_line(
+    out: &mut Vec<Line>,
+    line: &Line,
+    line_idx: usize,
+    diag: &Diagnostic,
+    gutter: &impl Fn(Option<usize>) -> (syntect::highlighting::Style, String),
+    fg: Rgb<u8>,
+    bg: Rgb<u8>,
+) {
+    let mut numbered = vec![gutter(Some(line_idx + 1))];
+    numbered.extend(line.iter().cloned());
+    out.push(numbered);
+
+    let len = line_chars(line);
+    let underlines: Vec<Underline> = diag
+        .labels
+        .iter()
+        .filter(|l| (l.line..=l.end_line).contains(&line_idx))
+        .map(|l| {
+            let start = if line_idx == l.line { l.start } else { 0 };
+            let end = if line_idx == l.end_line { l.end } else { len };
+            Underline {
+                start,
+                end: end.max(start + 1),
+                primary: l.primary,
+                text: if line_idx == l.end_line { &l.text } else { "" },
+            }
+        })
+        .collect();
+    for row in underline_rows(underlines, &diag.level, fg, bg) {
+        let mut annotated = vec![gutter(None)];
+        annotated.extend(row);
+        out.push(annotated);
+    }
+}
+
+/// Rows drawn beneath one source line: the first holds every underline and
+/// the rightmost label; each further label gets a `|` connector row and a
+/// row of its own, as rustc does.
+fn underline_rows(
+    mut underlines: Vec<Underline>,
+    level: &str,
+    fg: Rgb<u8>,
+    bg: Rgb<u8>,
+) -> Vec<Line> {
+    if underlines.is_empty() {
+        return Vec::new();
+    }
+    underlines.sort_by_key(|u| u.start);
+    let primary_style = plain_style(level_color(level), true);
+    let secondary_style = plain_style(MarkKind::Info.color(), true);
+    let style = |u: &Underline| {
+        if u.primary {
+            primary_style
+        } else {
+            secondary_style
+        }
+    };
+    let label_text = plain_style(blend(fg, bg, 0.9), false);
+
+    // Cells of a row as (column, style, text), rendered left to right
+    let render = |mut cells: Vec<(usize, syntect::highlighting::Style, String)>| -> Line {
+        cells.sort_by_key(|c| c.0);
+        let mut line: Line = Vec::new();
+        let mut col = 0;
+        for (start, style, text) in cells {
+            // A cell starting inside an earlier one would be drawn out of
+            // place; the earlier cell stands for both
+            if start < col {
+                continue;
+            }
+            if start > col {
+                line.push((label_text, " ".repeat(start - col)));
+                col = start;
+            }
+            col += text.chars().count();
+            line.push((style, text));
+        }
+        line
+    };
+
+    // Overlapping and nested underlines merge into one row, with the
+    // primary `^` winning over `-` where they meet
+    let width = underlines.iter().map(|u| u.end).max().unwrap_or(0);
+    let mut marks: Vec<Option<bool>> = vec![None; width];
+    for u in &underlines {
+        for mark in &mut marks[u.start..u.end] {
+            *mark = Some(mark.unwrap_or(false) || u.primary);
+        }
+    }
+    let mut first = Vec::new();
+    let mut col = 0;
+    while col < width {
+        let run = marks[col..]
+            .iter()
+            .take_while(|&&m| m == marks[col])
+            .count();
+        if let Some(primary) = marks[col] {
+            let (marker, style) = if primary {
+                ("^", primary_style)
+            } else {
+                ("-", secondary_style)
+            };
+            first.push((col, style, marker.repeat(run)));
+        }
+        col += run;
+    }
+
+    let mut rows = Vec::new();
+    // The rightmost label fits on the underline row itself
+    let mut pending: Vec<&Underline> = underlines.iter().filter(|u| !u.text.is_empty()).collect();
+    if let Some(i) = pending.iter().rposition(|u| u.end == width) {
+        let last = pending.remove(i);
+        first.push((width + 1, style(last), last.text.to_string()));
+    }
+    rows.push(render(first));
+
+    // Remaining labels from right to left, each hanging off a connector
+    while let Some(label) = pending.pop() {
+        let connectors = |upto: usize| -> Vec<_> {
+            pending[..upto]
+                .iter()
+                .filter(|u| u.start != label.start)
+                .map(|u| (u.start, style(u), "|".to_string()))
+                .collect()
+        };
+        let mut connector_row = connectors(pending.len());
+        connector_row.push((label.start, style(label), "|".to_string()));
+        rows.push(render(connector_row));
+        let mut label_row = connectors(pending.len());
+        label_row.push((label.start, style(label), label.text.to_string()));
+        rows.push(render(label_row));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &Line) -> String {
+        line.iter().map(|(_, t)| t.as_str()).collect()
+    }
+
+    #[test]
+    fn byte_offsets_to_columns() {
+        let source = "let é = \"日本\";\nfoo\n";
+        let index = SourceIndex::new(source);
+        let byte = |s: &str| source.find(s).unwrap();
+        assert_eq!(index.position(byte("=")), Some((0, 6)));
+        assert_eq!(index.position(byte("本")), Some((0, 10)));
+        assert_eq!(index.position(byte(";")), Some((0, 12)));
+        // An offset inside a character counts as that character
+        assert_eq!(index.position(byte("本") + 1), Some((0, 10)));
+        assert_eq!(index.position(byte("foo") + 1), Some((1, 1)));
+        assert_eq!(index.span(byte("日"), byte(";")), Some((0, 9, 0, 12)));
+        assert_eq!(index.span(0, source.len()), Some((0, 0, 1, 4)));
+        assert_eq!(index.span(0, source.len() + 1), None);
+        assert_eq!(index.span(5, 4), None);
+    }
+
+    #[test]
+    fn rustc_spans_in_multibyte_source() {
+        let source = "let é = \"日本\";\n";
+        let start = source.find('"').unwrap();
+        let end = source.find(';').unwrap();
+        let json = format!(
+            r#"{{"message": "mismatched types", "code": {{"code": "E0308"}}, "level": "error",
+                 "spans": [{{"file_name": "src/main.rs", "byte_start": {start}, "byte_end": {end},
+                             "is_primary": true, "label": "expected `u8`"}}],
+                 "children": []}}"#
+        )
+        .replace('\n', " ");
+        let diagnostics = parse(&json, source, "/work/app/src/main.rs").unwrap();
+        let label = &diagnostics[0].labels[0];
+        assert_eq!((label.line, label.start, label.end), (0, 8, 12));
+        assert!(parse(&json, source, "src/lib.rs").is_err());
+    }
+
+    #[test]
+    fn matching_files() {
+        assert!(same_file("src/main.rs", "/work/app/src/main.rs"));
+        assert!(same_file("file:///work/app/src/main.rs", "src/main.rs"));
+        assert!(same_file("anything.rs", "-"));
+        assert!(!same_file("src/main.rs", "main.rs.bak"));
+        assert!(!same_file("src/main.rs", "tests/main.rs"));
+    }
+
+    #[test]
+    fn labeled_lines_and_context() {
+        let label = |line, end_line| Label {
+            line,
+            start: 0,
+            end_line,
+            end: 1,
+            primary: true,
+            text: String::new(),
+        };
+        assert_eq!(
+            visible_lines(&[label(0, 0), label(10, 12)], 12),
+            [0, 1, 9, 10, 11]
+        );
+    }
+
+    #[test]
+    fn underlines_and_labels() {
+        let (fg, bg) = (Rgb([255, 255, 255]), Rgb([0, 0, 0]));
+        let underline = |start, end, primary, text| Underline {
+            start,
+            end,
+            primary,
+            text,
+        };
+        let rows = underline_rows(
+            vec![
+                underline(8, 12, true, "expected `u8`"),
+                underline(4, 5, false, "binding"),
+            ],
+            "error",
+            fg,
+            bg,
+        );
+        let rows: Vec<String> = rows.iter().map(text).collect();
+        assert_eq!(
+            rows,
+            ["    -   ^^^^ expected `u8`", "    |", "    binding",]
+        );
+    }
+
+    #[test]
+    fn nested_underlines_merge() {
+        let (fg, bg) = (Rgb([255, 255, 255]), Rgb([0, 0, 0]));
+        let underline = |start, end, primary, text| Underline {
+            start,
+            end,
+            primary,
+            text,
+        };
+        let rows = underline_rows(
+            vec![
+                underline(4, 10, true, "outer"),
+                underline(6, 8, false, "inner"),
+            ],
+            "error",
+            fg,
+            bg,
+        );
+        let rows: Vec<String> = rows.iter().map(text).collect();
+        assert_eq!(rows, ["    ^^^^^^ outer", "      |", "      inner"]);
+
+        // Where a secondary span sticks out of the primary one, it keeps `-`
+        let rows = underline_rows(
+            vec![
+                underline(2, 6, false, "borrowed here"),
+                underline(4, 10, true, "moved here"),
+            ],
+            "error",
+            fg,
+            bg,
+        );
+        let rows: Vec<String> = rows.iter().map(text).collect();
+        assert_eq!(rows, ["  --^^^^^^ moved here", "  |", "  borrowed here"]);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::render::{blend, Metrics};
+use ab_glyph::{Font, PxScale, ScaleFont};
+use clap::ValueEnum;
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage, RgbaImage};
+use imageproc::drawing::draw_text_mut;
+
+/// Horizontal placement of the footer text.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// Image corner a logo is placed in.
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Extend `img` downwards with a band of `band_bg` holding `text` in a small,
+/// dim font. With `inset` the band continues the code area, whose own bottom
+/// padding already separates the footer from the code; otherwise the text
+/// gets a half padding margin above it as well.
+pub fn add_footer(
+    metrics: &Metrics,
+    img: &RgbImage,
+    text: &str,
+    align: Align,
+    band_bg: Rgb<u8>,
+    fg: Rgb<u8>,
+    inset: bool,
+) -> RgbImage {
+    let scale = PxScale::from(metrics.scale.y * 0.6);
+    let above = if inset { 0 } else { metrics.padding / 2 };
+    let band_height = above + scale.y.ceil() as u32 + metrics.padding / 2;
+
+    let (width, height) = img.dimensions();
+    let mut out = RgbImage::from_pixel(width, height + band_height, band_bg);
+    imageops::replace(&mut out, img, 0, 0);
+
+    let advance = metrics
+        .font
+        .as_scaled(scale)
+        .h_advance(metrics.font.glyph_id('M'));
+    let text_width = text.chars().count() as f32 * advance;
+    let x = match align {
+        Align::Left => metrics.padding as f32,
+        Align::Center => (width as f32 - text_width) / 2.0,
+        Align::Right => width as f32 - metrics.padding as f32 - text_width,
+    };
+    let y = height + above;
+    let color = blend(fg, band_bg, 0.5);
+    draw_text_mut(
+        &mut out,
+        color,
+        x.max(0.0) as i32,
+        y as i32,
+        scale,
+        &metrics.font,
+        text,
+    );
+    out
+}
+
+/// Composite `logo` into `corner` of `img`, `margin` pixels from both edges,
+/// scaled down to at most `max_height` and drawn at `opacity`.
+pub fn overlay_logo(
+    img: &mut RgbImage,
+    logo: &RgbaImage,
+    corner: Corner,
+    opacity: f32,
+    max_height: u32,
+    margin: u32,
+) {
+    let scaled;
+    let logo = if logo.height() > max_height {
+        let width = (logo.width() as f32 * max_height as f32 / logo.height() as f32).round();
+        scaled = imageops::resize(
+            logo,
+            (width as u32).max(1),
+            max_height,
+            FilterType::Lanczos3,
+        );
+        &scaled
+    } else {
+        logo
+    };
+
+    let (width, height) = img.dimensions();
+    let x = match corner {
+        Corner::TopLeft | Corner::BottomLeft => margin,
+        Corner::TopRight | Corner::BottomRight => width.saturating_sub(logo.width() + margin),
+    };
+    let y = match corner {
+        Corner::TopLeft | Corner::TopRight => margin,
+        Corner::BottomLeft | Corner::BottomRight => height.saturating_sub(logo.height() + margin),
+    };
+    for (lx, ly, pixel) in logo.enumerate_pixels() {
+        let (px, py) = (x + lx, y + ly);
+        if px >= width || py >= height {
+            continue;
+        }
+        let [r, g, b, a] = pixel.0;
+        let alpha = a as f32 / 255.0 * opacity.clamp(0.0, 1.0);
+        let under = *img.get_pixel(px, py);
+        img.put_pixel(px, py, blend(Rgb([r, g, b]), under, alpha));
+    }
+}
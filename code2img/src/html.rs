@@ -0,0 +1,75 @@
+use crate::render::{Line, Metrics};
+use image::Rgb;
+use syntect::highlighting::FontStyle;
+
+/// Font stack for the HTML output: the font embedded in PNGs first, then
+/// common monospace fallbacks.
+const FONT_STACK: &str =
+    "'JetBrains Mono', ui-monospace, SFMono-Regular, Menlo, Consolas, monospace";
+
+fn hex(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Standalone `<pre>` block with inline styles, laid out like the PNG:
+/// same padding, background, font size and line height. Span styles follow
+/// the PNG renderer too: backgrounds only when opaque, bold, italic and
+/// underline.
+pub fn to_html(metrics: &Metrics, lines: &[Line], bg: Rgb<u8>, fg: Rgb<u8>) -> String {
+    let mut html = format!(
+        "<pre style=\"margin: 0; padding: {}px; display: inline-block; background: {}; \
+         color: {}; font-family: {}; font-size: {}px; line-height: {}px; tab-size: 4\"><code>",
+        metrics.padding,
+        hex(bg),
+        hex(fg),
+        FONT_STACK,
+        metrics.scale.y,
+        metrics.line_height
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        for (style, text) in line {
+            let text = text.trim_end_matches('\n').trim_end_matches('\r');
+            if text.is_empty() {
+                continue;
+            }
+            let color = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
+            let mut css = Vec::new();
+            if color != fg {
+                css.push(format!("color: {}", hex(color)));
+            }
+            if style.background.a > 0 {
+                let background = Rgb([style.background.r, style.background.g, style.background.b]);
+                css.push(format!("background: {}", hex(background)));
+            }
+            if style.font_style.contains(FontStyle::BOLD) {
+                css.push("font-weight: bold".to_string());
+            }
+            if style.font_style.contains(FontStyle::ITALIC) {
+                css.push("font-style: italic".to_string());
+            }
+            if style.font_style.contains(FontStyle::UNDERLINE) {
+                css.push("text-decoration: underline".to_string());
+            }
+            if css.is_empty() {
+                html.push_str(&escape(text));
+            } else {
+                html.push_str(&format!(
+                    "<span style=\"{}\">{}</span>",
+                    css.join("; "),
+                    escape(text)
+                ));
+            }
+        }
+    }
+    html.push_str("</code></pre>\n");
+    html
+}
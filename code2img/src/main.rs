@@ -1,27 +1,66 @@
-use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
-use clap::Parser;
+mod anim;
+mod ansi;
+mod cast;
+mod compare;
+mod contrast;
+mod diagnostic;
+mod footer;
+mod html;
+mod marks;
+mod pdf;
+mod preview;
+mod render;
+mod shell;
+mod transition;
+mod treesitter;
+
+use anim::AnimFrame;
+use clap::{Parser, Subcommand, ValueEnum};
 use image::{Rgb, RgbImage};
-use imageproc::drawing::draw_text_mut;
+use render::{Line, Metrics};
 use std::fs;
+use std::num::NonZeroUsize;
+use std::path::Path;
 use syntect::easy::HighlightLines;
-use syntect::highlighting::{Style, ThemeSet};
-use syntect::parsing::SyntaxSet;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 use syntect::util::LinesWithEndings;
 
 #[derive(Parser)]
-#[command(name = "code2img", about = "Render a code block to a PNG image")]
+#[command(
+    name = "code2img",
+    about = "Render a code block to a PNG image",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
 struct Cli {
-    /// Input file containing code (or - for stdin)
-    #[arg(short, long)]
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Input file containing code (or - for stdin). Pass twice to render two
+    /// inputs side by side
+    #[arg(
+        short,
+        long,
+        num_args = 1,
+        required_unless_present = "steps",
+        conflicts_with = "steps"
+    )]
+    input: Vec<String>,
 
     /// Output PNG path
-    #[arg(short, long)]
-    output: String,
+    #[arg(short, long, required = true)]
+    output: Option<String>,
+
+    /// Output format (defaults to html for .html/.htm outputs, png otherwise);
+    /// html writes a standalone <pre> block with inline styles
+    #[arg(long, value_enum)]
+    format: Option<Format>,
 
-    /// Language for syntax highlighting (e.g. rust, python, javascript)
-    #[arg(short, long, default_value = "plain")]
-    lang: String,
+    /// Language for syntax highlighting (e.g. rust, python, javascript). Pass
+    /// once per input to highlight side-by-side inputs differently
+    #[arg(short, long, num_args = 1, default_value = "plain")]
+    lang: Vec<String>,
 
     /// Font size in pixels
     #[arg(long, default_value = "28")]
@@ -30,91 +69,565 @@ struct Cli {
     /// Theme name (e.g. base16-ocean.dark, Solarized (dark), InspiredGitHub)
     #[arg(long, default_value = "base16-ocean.dark")]
     theme: String,
+
+    /// Highlighting engine; tree-sitter covers Rust, TypeScript/TSX, JavaScript,
+    /// Python, Go, JSON and Bash and falls back to syntect for other languages
+    #[arg(long, value_enum, default_value = "syntect")]
+    highlighter: Backend,
+
+    /// Render an animation instead of a still image (GIF for .gif outputs, APNG otherwise)
+    #[arg(long, value_enum)]
+    animate: Option<Animation>,
+
+    /// Typing speed for --animate typing, in characters per second
+    #[arg(long, default_value = "30")]
+    cps: f32,
+
+    /// Blink the cursor while holding the final frame
+    #[arg(long)]
+    cursor_blink: bool,
+
+    /// How long the finished code stays on screen before the animation loops, in milliseconds
+    #[arg(long, default_value = "2000")]
+    hold_ms: u32,
+
+    /// Code revision to animate between; pass two or more in order
+    /// (e.g. --step a.rs --step b.rs)
+    #[arg(
+        long = "step",
+        value_name = "FILE",
+        num_args = 1,
+        conflicts_with = "animate"
+    )]
+    steps: Vec<String>,
+
+    /// Duration of each transition between --step revisions, in milliseconds
+    #[arg(long, default_value = "800")]
+    transition_ms: u32,
+
+    /// Treat input as terminal output: render ANSI colors and attributes instead of
+    /// syntax highlighting
+    #[arg(long)]
+    ansi: bool,
+
+    /// Terminal color palette for --ansi (base16-ocean, xterm, vscode, tango,
+    /// solarized-dark, dracula)
+    #[arg(long, default_value = "base16-ocean")]
+    palette: String,
+
+    /// Treat input as an asciinema v2 .cast recording and replay it into an
+    /// animated GIF/APNG
+    #[arg(long, conflicts_with_all = ["steps", "animate"])]
+    cast: bool,
+
+    /// Longest pause kept from a --cast recording, in seconds (defaults to the
+    /// recording's idle_time_limit, or 2)
+    #[arg(long)]
+    idle_limit: Option<f64>,
+
+    /// Treat input as a shell session: `$ `, `# ` and `> ` lines are commands
+    /// highlighted as bash, other lines are output (implied by -l console)
+    #[arg(long)]
+    shell_session: bool,
+
+    /// Wrap the image in a terminal window frame
+    #[arg(long)]
+    frame: bool,
+
+    /// Split long input into pages of at most N lines, written as
+    /// <output>-1.png, <output>-2.png, ...
+    #[arg(long, value_name = "N", conflicts_with_all = ["animate", "steps", "cast"])]
+    max_lines: Option<NonZeroUsize>,
+
+    /// Column header for side-by-side inputs, once per input (e.g. --title Before
+    /// --title After)
+    #[arg(long, num_args = 1)]
+    title: Vec<String>,
+
+    /// Separator between side-by-side columns
+    #[arg(long, value_enum, default_value = "line")]
+    divider: compare::Divider,
+
+    /// Mark every match of a regex, drawn as a highlighter box or, with an
+    /// error:, warning: or info: prefix, as a squiggly underline
+    #[arg(
+        long,
+        value_name = "[KIND:]REGEX",
+        num_args = 1,
+        conflicts_with_all = ["animate", "steps", "cast"]
+    )]
+    mark: Vec<String>,
+
+    /// Mark a character range given as line:column-line:column (1-based, end
+    /// exclusive), with the same optional kind prefix as --mark
+    #[arg(
+        long,
+        value_name = "[KIND:]L:C-L:C",
+        num_args = 1,
+        conflicts_with_all = ["animate", "steps", "cast"]
+    )]
+    mark_range: Vec<String>,
+
+    /// Compiler diagnostic for the input (rustc --error-format=json output or a
+    /// SARIF log), drawn inline with carets and labels
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["mark", "mark_range", "steps", "cast"])]
+    diagnostic: Option<String>,
+
+    /// Report theme colors whose WCAG contrast against the background is below
+    /// --min-contrast (or 4.5:1), per scope, on stderr
+    #[arg(long, conflicts_with_all = ["ansi", "cast"])]
+    check_contrast: bool,
+
+    /// Lighten or darken theme colors below this WCAG contrast ratio, keeping
+    /// their hue, before rendering (e.g. 4.5)
+    #[arg(long, value_name = "RATIO", conflicts_with_all = ["ansi", "cast"])]
+    min_contrast: Option<f64>,
+
+    /// Small dim line below the code, e.g. an author or URL
+    #[arg(long)]
+    footer: Option<String>,
+
+    /// Horizontal alignment of --footer
+    #[arg(long, value_enum, default_value = "right")]
+    footer_align: footer::Align,
+
+    /// Draw --footer below the --frame window instead of inside it
+    #[arg(long, requires = "frame")]
+    footer_outside: bool,
+
+    /// Image composited as a watermark in one corner (scaled down to one line
+    /// height if larger)
+    #[arg(long, value_name = "PNG")]
+    logo: Option<String>,
+
+    /// Corner to place --logo in
+    #[arg(long, value_enum, default_value = "bottom-right")]
+    logo_corner: footer::Corner,
+
+    /// Opacity of --logo, from 0 to 1
+    #[arg(long, default_value = "0.8")]
+    logo_opacity: f32,
 }
 
-fn main() {
-    let cli = Cli::parse();
+#[derive(Subcommand)]
+enum Command {
+    /// List the available themes
+    Themes,
+    /// List the supported languages with the tokens and extensions -l accepts
+    Languages,
+    /// Render a sample snippet in every theme into one labeled grid image
+    ThemePreview {
+        /// Output PNG path
+        #[arg(short, long)]
+        output: String,
+
+        /// Font size in pixels
+        #[arg(long, default_value = "20")]
+        font_size: f32,
 
-    let code = if cli.input == "-" {
+        /// Number of themes per row
+        #[arg(long, default_value = "3")]
+        columns: usize,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    /// PNG image (GIF or APNG when animating)
+    Png,
+    /// Standalone HTML <pre> block with inline styles
+    Html,
+    /// Single-page vector PDF with selectable text
+    Pdf,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Backend {
+    /// TextMate grammars bundled with syntect
+    Syntect,
+    /// Bundled tree-sitter grammars and highlight queries
+    TreeSitter,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Animation {
+    /// Characters appear progressively, as if being typed
+    Typing,
+}
+
+fn read_input(path: &str) -> String {
+    if path == "-" {
         use std::io::Read;
         let mut buf = String::new();
         std::io::stdin().read_to_string(&mut buf).unwrap();
         buf
     } else {
-        fs::read_to_string(&cli.input).expect("Failed to read input file")
-    };
+        fs::read_to_string(path).expect("Failed to read input file")
+    }
+}
+
+fn highlight(code: &str, syntax: &SyntaxReference, theme: &Theme, ss: &SyntaxSet) -> Vec<Line> {
+    let mut h = HighlightLines::new(syntax, theme);
+
+    // Collect highlighted lines
+    let mut highlighted_lines: Vec<Line> = Vec::new();
+    for line in LinesWithEndings::from(code) {
+        let ranges = h.highlight_line(line, ss).unwrap();
+        let styled: Line = ranges
+            .into_iter()
+            .map(|(style, text)| (render::theme_colors(style), text.to_string()))
+            .collect();
+        highlighted_lines.push(styled);
+    }
+    highlighted_lines
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if cli.steps.len() == 1 {
+        eprintln!("--step needs at least two revisions to animate between");
+        std::process::exit(1);
+    }
+    if cli.input.len() > 2 {
+        eprintln!("At most two inputs can be rendered side by side");
+        std::process::exit(1);
+    }
+    let side_by_side = cli.input.len() == 2;
+    let marked = !cli.mark.is_empty() || !cli.mark_range.is_empty() || cli.diagnostic.is_some();
+    if side_by_side && (cli.animate.is_some() || cli.cast || cli.max_lines.is_some() || marked) {
+        eprintln!(
+            "Side-by-side inputs cannot be combined with --animate, --cast, --max-lines, --mark, \
+             --mark-range or --diagnostic"
+        );
+        std::process::exit(1);
+    }
+    if !side_by_side && !cli.title.is_empty() {
+        eprintln!("--title labels side-by-side inputs; pass two -i inputs to use it");
+        std::process::exit(1);
+    }
+    let format = cli.format.unwrap_or_else(|| {
+        let ext = cli
+            .output
+            .as_deref()
+            .and_then(|o| Path::new(o).extension())
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("html" | "htm") => Format::Html,
+            Some("pdf") => Format::Pdf,
+            _ => Format::Png,
+        }
+    });
+    let animated = cli.animate.is_some() || !cli.steps.is_empty() || cli.cast;
+    if format != Format::Png && (animated || side_by_side || cli.max_lines.is_some()) {
+        eprintln!(
+            "HTML and PDF output render a single snippet; they cannot be combined with animations, \
+             side-by-side inputs or --max-lines"
+        );
+        std::process::exit(1);
+    }
+    let drawn_over = !cli.mark.is_empty()
+        || !cli.mark_range.is_empty()
+        || cli.footer.is_some()
+        || cli.logo.is_some();
+    if format == Format::Html && (drawn_over || cli.frame) {
+        eprintln!(
+            "HTML output is the code block alone; it cannot be combined with --mark, \
+             --mark-range, --footer, --logo or --frame"
+        );
+        std::process::exit(1);
+    }
+    if format == Format::Pdf && drawn_over {
+        eprintln!(
+            "PDF output is the code block and its --frame; it cannot be combined with --mark, \
+             --mark-range, --footer or --logo"
+        );
+        std::process::exit(1);
+    }
 
     let ss = SyntaxSet::load_defaults_newlines();
     let ts = ThemeSet::load_defaults();
 
-    let theme = ts.themes.get(&cli.theme).unwrap_or_else(|| {
+    if let Some(command) = &cli.command {
+        run_command(command, &ss, &ts);
+        return;
+    }
+    let output = cli
+        .output
+        .as_deref()
+        .expect("clap requires -o without a subcommand");
+
+    let mut theme = ts.themes.get(&cli.theme).cloned().unwrap_or_else(|| {
         eprintln!(
-            "Theme '{}' not found. Available: {:?}",
-            cli.theme,
-            ts.themes.keys().collect::<Vec<_>>()
+            "Theme '{}' not found. Run `code2img themes` to list the available themes",
+            cli.theme
         );
         std::process::exit(1);
     });
+    if cli.check_contrast {
+        let min_ratio = cli.min_contrast.unwrap_or(contrast::AA);
+        eprint!(
+            "{}",
+            contrast::report(&theme, min_ratio, cli.min_contrast.is_some())
+        );
+    }
+    if let Some(min_ratio) = cli.min_contrast {
+        contrast::adjust_theme(&mut theme, min_ratio);
+    }
+    let theme = &theme;
 
-    let syntax = ss
-        .find_syntax_by_token(&cli.lang)
-        .unwrap_or_else(|| ss.find_syntax_plain_text());
+    // Inputs without their own --lang use the last one given
+    let lang_for = |i: usize| {
+        cli.lang
+            .get(i)
+            .or(cli.lang.last())
+            .map_or("plain", |l| l.as_str())
+    };
 
-    let mut h = HighlightLines::new(syntax, theme);
+    let palette = (cli.ansi || cli.cast).then(|| {
+        ansi::palette(&cli.palette).unwrap_or_else(|| {
+            eprintln!(
+                "Palette '{}' not found. Available: {:?}",
+                cli.palette,
+                ansi::PALETTE_NAMES
+            );
+            std::process::exit(1);
+        })
+    });
+    let highlight_text = |text: &str, lang: &str| {
+        let syntax = ss
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| ss.find_syntax_plain_text());
+        match &palette {
+            Some(palette) => ansi::parse(text, palette),
+            None if cli.shell_session || matches!(lang, "console" | "shell-session") => {
+                shell::highlight_session(text, &ss, theme)
+            }
+            None if cli.highlighter == Backend::TreeSitter => {
+                treesitter::highlight(text, lang, theme)
+                    .unwrap_or_else(|| highlight(text, syntax, theme, &ss))
+            }
+            None => highlight(text, syntax, theme, &ss),
+        }
+    };
+    let load = |path: &str, lang: &str| highlight_text(&read_input(path), lang);
 
-    // Collect highlighted lines
-    let mut highlighted_lines: Vec<Vec<(Style, String)>> = Vec::new();
-    for line in LinesWithEndings::from(&code) {
-        let ranges = h.highlight_line(line, &ss).unwrap();
-        let styled: Vec<(Style, String)> = ranges
+    let metrics = Metrics::new(cli.font_size);
+    let (bg, fg) = match &palette {
+        Some(p) => (Rgb([p.bg.r, p.bg.g, p.bg.b]), Rgb([p.fg.r, p.fg.g, p.fg.b])),
+        None => (render::background(theme), render::foreground(theme)),
+    };
+    let logo = cli.logo.as_ref().map(|path| {
+        image::open(path)
+            .expect("Failed to read logo image")
+            .to_rgba8()
+    });
+    // Footer, window frame and logo are added around every rendered image
+    let decorate = |mut img: RgbImage| {
+        if let Some(text) = cli.footer.as_deref().filter(|_| !cli.footer_outside) {
+            img = footer::add_footer(&metrics, &img, text, cli.footer_align, bg, fg, true);
+        }
+        if cli.frame {
+            img = metrics.window_frame(&img, bg);
+        }
+        if let Some(text) = cli.footer.as_deref().filter(|_| cli.footer_outside) {
+            let band = render::shade(bg, 0.6);
+            img = footer::add_footer(&metrics, &img, text, cli.footer_align, band, fg, false);
+        }
+        if let Some(logo) = &logo {
+            footer::overlay_logo(
+                &mut img,
+                logo,
+                cli.logo_corner,
+                cli.logo_opacity,
+                metrics.line_height,
+                metrics.padding / 2,
+            );
+        }
+        img
+    };
+    let save_frames = |frames: Vec<AnimFrame>| {
+        let frames = frames
             .into_iter()
-            .map(|(style, text)| (style, text.to_string()))
+            .map(|f| AnimFrame {
+                image: decorate(f.image),
+                ..f
+            })
             .collect();
-        highlighted_lines.push(styled);
+        anim::save(output, frames);
+    };
+
+    if cli.cast {
+        let palette = palette.as_ref().expect("--cast always resolves a palette");
+        let text = read_input(&cli.input[0]);
+        let recording = cast::parse(&text).unwrap_or_else(|e| {
+            eprintln!("Failed to read cast file: {}", e);
+            std::process::exit(1);
+        });
+        let idle_limit = cli.idle_limit.or(recording.idle_time_limit).unwrap_or(2.0);
+        let frames = cast::cast_frames(&metrics, &recording, palette, idle_limit, cli.hold_ms);
+        save_frames(frames);
+        println!("{}", output);
+        return;
     }
 
-    // Use embedded font (monospace)
-    let font_data = include_bytes!("../assets/JetBrainsMono-Regular.ttf");
-    let font = FontRef::try_from_slice(font_data).expect("Failed to load font");
-    let scale = PxScale::from(cli.font_size);
-    let scaled_font = font.as_scaled(scale);
-
-    // Get the actual advance width of a monospace character using 'M'
-    let glyph_id = font.glyph_id('M');
-    let char_advance = scaled_font.h_advance(glyph_id);
-
-    // Calculate image dimensions
-    let line_height = (cli.font_size * 1.5) as u32;
-    let padding = 32u32;
-    let max_line_len = code.lines().map(|l| l.len()).max().unwrap_or(0);
-    let img_width = (max_line_len as f32 * char_advance + padding as f32 * 2.0).max(400.0) as u32;
-    let img_height = (highlighted_lines.len() as u32 * line_height + padding * 2).max(100);
-
-    // Background color from theme
-    let bg = theme.settings.background.unwrap_or(syntect::highlighting::Color {
-        r: 43,
-        g: 48,
-        b: 59,
-        a: 255,
-    });
-    let mut img = RgbImage::from_pixel(img_width, img_height, Rgb([bg.r, bg.g, bg.b]));
-
-    // Draw each line with syntax colors
-    for (line_idx, spans) in highlighted_lines.iter().enumerate() {
-        let y = padding as i32 + (line_idx as i32 * line_height as i32);
-        let mut x_offset: f32 = padding as f32;
-
-        for (style, text) in spans {
-            let color = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
-            let draw_text_str = text.trim_end_matches('\n').trim_end_matches('\r');
-            if !draw_text_str.is_empty() {
-                draw_text_mut(&mut img, color, x_offset as i32, y, scale, &font, draw_text_str);
-                // Advance by actual glyph width * character count
-                x_offset += draw_text_str.chars().count() as f32 * char_advance;
+    if !cli.steps.is_empty() {
+        let steps: Vec<Vec<Line>> = cli
+            .steps
+            .iter()
+            .map(|path| load(path, lang_for(0)))
+            .collect();
+        let frames =
+            transition::transition_frames(&metrics, &steps, bg, cli.transition_ms, cli.hold_ms);
+        save_frames(frames);
+        println!("{}", output);
+        return;
+    }
+
+    if side_by_side {
+        let columns: Vec<Vec<Line>> = cli
+            .input
+            .iter()
+            .enumerate()
+            .map(|(i, path)| load(path, lang_for(i)))
+            .collect();
+        let img = compare::side_by_side(&metrics, &columns, &cli.title, bg, fg, cli.divider);
+        decorate(img).save(output).expect("Failed to save image");
+        println!("{}", output);
+        return;
+    }
+
+    // Read once: diagnostics map their spans onto the same text, and stdin
+    // cannot be read twice
+    let source = read_input(&cli.input[0]);
+    let mut highlighted_lines = highlight_text(&source, lang_for(0));
+
+    if let Some(path) = &cli.diagnostic {
+        let json = fs::read_to_string(path).expect("Failed to read diagnostic file");
+        let diagnostics = diagnostic::parse(&json, &source, &cli.input[0]).unwrap_or_else(|e| {
+            eprintln!("Failed to read diagnostic: {}", e);
+            std::process::exit(1);
+        });
+        let file = Path::new(&cli.input[0])
+            .file_name()
+            .map_or("<stdin>".into(), |f| f.to_string_lossy());
+        highlighted_lines = diagnostic::annotate(&file, &highlighted_lines, &diagnostics, fg, bg);
+    }
+
+    if format == Format::Html {
+        let html = html::to_html(&metrics, &highlighted_lines, bg, fg);
+        fs::write(output, html).expect("Failed to write output file");
+        println!("{}", output);
+        return;
+    }
+    if format == Format::Pdf {
+        let pdf = pdf::to_pdf(&metrics, &highlighted_lines, bg, cli.frame);
+        fs::write(output, pdf).expect("Failed to write output file");
+        println!("{}", output);
+        return;
+    }
+
+    let marks: Vec<marks::Mark> = cli
+        .mark
+        .iter()
+        .map(|spec| marks::find_matches(spec, &highlighted_lines))
+        .chain(
+            cli.mark_range
+                .iter()
+                .map(|spec| marks::parse_range(spec, &highlighted_lines)),
+        )
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| {
+            eprintln!("Invalid mark: {}", e);
+            std::process::exit(1);
+        })
+        .concat();
+    let box_color = marks::box_color(theme, bg);
+    let draw_page = |lines: &[Line], first_line: usize, width: u32, height: u32| {
+        let mut img = RgbImage::from_pixel(width, height, bg);
+        marks::draw_marks(&metrics, &mut img, lines, first_line, &marks, box_color);
+        metrics.draw_lines(&mut img, lines, None);
+        img
+    };
+
+    match cli.animate {
+        Some(Animation::Typing) => {
+            let typing = anim::Typing {
+                chars_per_second: cli.cps,
+                cursor_blink: cli.cursor_blink,
+                hold_ms: cli.hold_ms,
+            };
+            let frames = anim::typing_frames(&metrics, &highlighted_lines, bg, fg, &typing);
+            save_frames(frames);
+        }
+        None => {
+            let page_size = cli.max_lines.map_or(usize::MAX, NonZeroUsize::get);
+            if highlighted_lines.len() <= page_size {
+                let (img_width, img_height) = metrics.canvas_size(&highlighted_lines);
+                let img = draw_page(&highlighted_lines, 0, img_width, img_height);
+                decorate(img).save(output).expect("Failed to save image");
+            } else {
+                // Every page is as wide as the widest line in the whole input
+                let (img_width, _) = metrics.canvas_size(&highlighted_lines);
+                let pages: Vec<&[Line]> = highlighted_lines.chunks(page_size).collect();
+                for (i, page) in pages.iter().enumerate() {
+                    let (_, img_height) = metrics.canvas_size(page);
+                    let mut img = draw_page(page, i * page_size, img_width, img_height);
+                    let label = format!("{}/{}", i + 1, pages.len());
+                    metrics.draw_page_number(&mut img, &label, render::blend(fg, bg, 0.5));
+                    let path = numbered_path(output, i + 1);
+                    decorate(img).save(&path).expect("Failed to save image");
+                    println!("{}", path);
+                }
+                return;
+            }
+        }
+    }
+    println!("{}", output);
+}
+
+fn run_command(command: &Command, ss: &SyntaxSet, ts: &ThemeSet) {
+    match command {
+        Command::Themes => {
+            for name in ts.themes.keys() {
+                println!("{}", name);
+            }
+        }
+        Command::Languages => {
+            let mut syntaxes: Vec<&SyntaxReference> = ss.syntaxes().iter().collect();
+            syntaxes.sort_by_key(|s| s.name.to_lowercase());
+            let width = syntaxes.iter().map(|s| s.name.len()).max().unwrap_or(0);
+            println!("{:<width$}  {:<width$}  EXTENSIONS", "NAME", "TOKEN");
+            for syntax in syntaxes {
+                // -l matches an extension or the lowercased name
+                println!(
+                    "{:<width$}  {:<width$}  {}",
+                    syntax.name,
+                    syntax.name.to_lowercase(),
+                    syntax.file_extensions.join(", ")
+                );
             }
         }
+        Command::ThemePreview {
+            output,
+            font_size,
+            columns,
+        } => {
+            let sheet = preview::contact_sheet(ts, ss, *font_size, *columns);
+            sheet.save(output).expect("Failed to save image");
+            println!("{}", output);
+        }
     }
+}
 
-    img.save(&cli.output).expect("Failed to save image");
-    println!("{}", cli.output);
+/// `out.png` -> `out-<n>.png`
+fn numbered_path(output: &str, n: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, n, ext),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
 }
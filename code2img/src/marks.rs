@@ -0,0 +1,271 @@
+use crate::render::{blend, line_text, Line, Metrics};
+use ab_glyph::{Font, ScaleFont};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut};
+use imageproc::rect::Rect;
+use regex::Regex;
+use syntect::highlighting::Theme;
+
+/// How a marked range is drawn.
+#[derive(Clone, Copy, PartialEq)]
+pub enum MarkKind {
+    /// Rounded highlighter box behind the text
+    Box,
+    /// Red squiggle underneath
+    Error,
+    /// Yellow squiggle underneath
+    Warning,
+    /// Blue squiggle underneath
+    Info,
+}
+
+impl MarkKind {
+    /// Split an optional `error:`, `warning:`, `info:` or `box:` prefix off
+    /// a `--mark` / `--mark-range` value.
+    fn split(spec: &str) -> (MarkKind, &str) {
+        let kinds = [
+            ("box:", MarkKind::Box),
+            ("error:", MarkKind::Error),
+            ("warning:", MarkKind::Warning),
+            ("info:", MarkKind::Info),
+        ];
+        for (prefix, kind) in kinds {
+            if let Some(rest) = spec.strip_prefix(prefix) {
+                return (kind, rest);
+            }
+        }
+        (MarkKind::Box, spec)
+    }
+
+    /// Squiggle color; boxes use the theme's highlight color instead.
+    pub fn color(self) -> Rgb<u8> {
+        match self {
+            MarkKind::Box | MarkKind::Warning => Rgb([232, 191, 62]),
+            MarkKind::Error => Rgb([236, 95, 103]),
+            MarkKind::Info => Rgb([102, 153, 204]),
+        }
+    }
+}
+
+/// A character range on one line, in 0-based line and column indices with
+/// an exclusive end column.
+#[derive(Clone, Copy)]
+pub struct Mark {
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+    pub kind: MarkKind,
+}
+
+/// Parse `[kind:]L:C-L:C` (1-based, end column exclusive). A range spanning
+/// several lines marks the rest of the first line, every line in between
+/// and the start of the last line.
+pub fn parse_range(spec: &str, lines: &[Line]) -> Result<Vec<Mark>, String> {
+    let (kind, range) = MarkKind::split(spec);
+    let position = |p: &str| -> Option<(usize, usize)> {
+        let (l, c) = p.split_once(':')?;
+        let (l, c) = (
+            l.trim().parse::<usize>().ok()?,
+            c.trim().parse::<usize>().ok()?,
+        );
+        (l > 0 && c > 0).then(|| (l - 1, c - 1))
+    };
+    let bad = || format!("invalid range '{}', expected L:C-L:C", range);
+    let (from, to) = range.split_once('-').ok_or_else(bad)?;
+    let ((l1, c1), (l2, c2)) = (
+        position(from).ok_or_else(bad)?,
+        position(to).ok_or_else(bad)?,
+    );
+    if (l2, c2) < (l1, c1) {
+        return Err(bad());
+    }
+
+    let len = |l: usize| {
+        lines
+            .get(l)
+            .map_or(0, |line| line_text(line).chars().count())
+    };
+    Ok((l1..=l2)
+        .map(|line| Mark {
+            line,
+            start: if line == l1 { c1 } else { 0 },
+            end: if line == l2 { c2 } else { len(line) },
+            kind,
+        })
+        .filter(|m| m.end > m.start)
+        .collect())
+}
+
+/// Box fill for `theme`: its find or selection highlight over the background,
+/// or a soft yellow when the theme defines neither.
+pub fn box_color(theme: &Theme, bg: Rgb<u8>) -> Rgb<u8> {
+    match theme.settings.find_highlight.or(theme.settings.selection) {
+        Some(c) => blend(Rgb([c.r, c.g, c.b]), bg, c.a as f32 / 255.0),
+        None => blend(MarkKind::Box.color(), bg, 0.3),
+    }
+}
+
+/// Find every match of `[kind:]regex` on each line.
+pub fn find_matches(spec: &str, lines: &[Line]) -> Result<Vec<Mark>, String> {
+    let (kind, pattern) = MarkKind::split(spec);
+    let re = Regex::new(pattern).map_err(|e| e.to_string())?;
+    let mut marks = Vec::new();
+    for (line_idx, line) in lines.iter().enumerate() {
+        let text = line_text(line);
+        let column = |byte: usize| text[..byte].chars().count();
+        for m in re.find_iter(&text).filter(|m| !m.is_empty()) {
+            marks.push(Mark {
+                line: line_idx,
+                start: column(m.start()),
+                end: column(m.end()),
+                kind,
+            });
+        }
+    }
+    Ok(marks)
+}
+
+/// Draw `marks` for the lines starting at `first_line` of the input, filling
+/// boxes with `box_color`. Call before drawing the text so boxes sit behind it.
+pub fn draw_marks(
+    metrics: &Metrics,
+    img: &mut RgbImage,
+    lines: &[Line],
+    first_line: usize,
+    marks: &[Mark],
+    box_color: Rgb<u8>,
+) {
+    for mark in marks {
+        let Some(row) = mark
+            .line
+            .checked_sub(first_line)
+            .filter(|&r| r < lines.len())
+        else {
+            continue;
+        };
+        let y = metrics.line_y(row as f32);
+        let x0 = metrics.char_x(&lines[row], mark.start);
+        let x1 = metrics.char_x(&lines[row], mark.end);
+        match mark.kind {
+            MarkKind::Box => {
+                let pad = metrics.char_advance / 6.0;
+                let top = y - ((metrics.line_height as f32 - metrics.scale.y) / 2.0) as i32 + 2;
+                let height = metrics.line_height.saturating_sub(4);
+                let width = (x1 - x0 + pad * 2.0) as u32;
+                fill_rounded_rect(img, (x0 - pad) as i32, top, width, height, 6, box_color);
+            }
+            _ => {
+                let baseline = y as f32 + metrics.font.as_scaled(metrics.scale).ascent();
+                draw_squiggle(
+                    img,
+                    x0 as i32,
+                    x1 as i32,
+                    baseline as i32 + 3,
+                    mark.kind.color(),
+                );
+            }
+        }
+    }
+}
+
+fn fill_rounded_rect(
+    img: &mut RgbImage,
+    x: i32,
+    y: i32,
+    w: u32,
+    h: u32,
+    radius: u32,
+    color: Rgb<u8>,
+) {
+    let r = radius.min(w / 2).min(h / 2);
+    if w > r * 2 {
+        draw_filled_rect_mut(img, Rect::at(x + r as i32, y).of_size(w - r * 2, h), color);
+    }
+    if h > r * 2 {
+        draw_filled_rect_mut(img, Rect::at(x, y + r as i32).of_size(w, h - r * 2), color);
+    }
+    if r > 0 {
+        let (r, right, bottom) = (
+            r as i32,
+            x + w as i32 - r as i32 - 1,
+            y + h as i32 - r as i32 - 1,
+        );
+        for center in [
+            (x + r, y + r),
+            (right, y + r),
+            (x + r, bottom),
+            (right, bottom),
+        ] {
+            draw_filled_circle_mut(img, center, r, color);
+        }
+    }
+}
+
+/// A zig-zag underline from `x0` to `x1` just below `y`, as editors draw
+/// diagnostics.
+fn draw_squiggle(img: &mut RgbImage, x0: i32, x1: i32, y: i32, color: Rgb<u8>) {
+    const PERIOD: i32 = 6;
+    const AMPLITUDE: i32 = 2;
+    for x in x0..x1 {
+        let phase = (x - x0) % PERIOD;
+        let offset = if phase < PERIOD / 2 {
+            phase
+        } else {
+            PERIOD - phase
+        };
+        let dy = offset * AMPLITUDE * 2 / (PERIOD / 2) - AMPLITUDE;
+        for thickness in 0..2 {
+            let py = y + dy + thickness;
+            if x >= 0 && (x as u32) < img.width() && py >= 0 && (py as u32) < img.height() {
+                img.put_pixel(x as u32, py as u32, color);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::Style;
+
+    fn lines(text: &[&str]) -> Vec<Line> {
+        text.iter()
+            .map(|t| vec![(Style::default(), format!("{}\n", t))])
+            .collect()
+    }
+
+    fn ranges(marks: &[Mark]) -> Vec<(usize, usize, usize)> {
+        marks.iter().map(|m| (m.line, m.start, m.end)).collect()
+    }
+
+    #[test]
+    fn single_line_range() {
+        let code = lines(&["fn main() {", "    let x = 1;", "}"]);
+        let marks = parse_range("2:9-2:10", &code).unwrap();
+        assert_eq!(ranges(&marks), [(1, 8, 9)]);
+        assert!(marks[0].kind == MarkKind::Box);
+        let marks = parse_range("error: 1:4 - 1:8", &code).unwrap();
+        assert_eq!(ranges(&marks), [(0, 3, 7)]);
+        assert!(marks[0].kind == MarkKind::Error);
+    }
+
+    #[test]
+    fn multi_line_range() {
+        let code = lines(&["fn main() {", "    let x = 1;", "}"]);
+        let marks = parse_range("warning:1:4-3:2", &code).unwrap();
+        assert_eq!(ranges(&marks), [(0, 3, 11), (1, 0, 14), (2, 0, 1)]);
+        // Ranges ending at the start of a line leave that line unmarked
+        let marks = parse_range("1:1-2:1", &code).unwrap();
+        assert_eq!(ranges(&marks), [(0, 0, 11)]);
+    }
+
+    #[test]
+    fn invalid_ranges() {
+        let code = lines(&["abc"]);
+        for spec in [
+            "1:1", "1-2", "0:1-1:2", "1:0-1:2", "a:1-1:2", "1:3-1:2", "2:1-1:5",
+        ] {
+            assert!(parse_range(spec, &code).is_err(), "{}", spec);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use crate::render::{shade, Line, Metrics, FONT_DATA, ITALIC_PIVOT, ITALIC_SLANT};
+use ab_glyph::{Font, GlyphId, ScaleFont};
+use image::Rgb;
+use pdf_writer::types::{CidFontType, FontFlags, SystemInfo, TextRenderingMode, UnicodeCmap};
+use pdf_writer::{Content, Finish, Name, Pdf, Rect, Ref, Str};
+use std::collections::BTreeMap;
+use syntect::highlighting::FontStyle;
+
+/// PDF points per image pixel, so a snippet prints at the size it has on a
+/// 96 dpi screen.
+const PT_PER_PX: f32 = 0.75;
+
+/// Subset fonts are named with a six letter tag in front of the font name.
+const FONT_NAME: Name = Name(b"CDIMGA+JetBrainsMono-Regular");
+
+const SYSTEM_INFO: SystemInfo = SystemInfo {
+    registry: Str(b"Adobe"),
+    ordering: Str(b"Identity"),
+    supplement: 0,
+};
+
+/// Drawing commands in image pixel coordinates (origin top left), on a page
+/// `height` pixels tall.
+struct Canvas {
+    content: Content,
+    height: f32,
+}
+
+impl Canvas {
+    fn fill(&mut self, color: Rgb<u8>) {
+        let [r, g, b] = color.0.map(|c| c as f32 / 255.0);
+        self.content.set_fill_rgb(r, g, b);
+        self.content.set_stroke_rgb(r, g, b);
+    }
+
+    fn rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Rgb<u8>) {
+        self.fill(color);
+        self.content
+            .rect(x, self.height - y - height, width, height)
+            .fill_nonzero();
+    }
+
+    fn circle(&mut self, (cx, cy): (i32, i32), radius: i32, color: Rgb<u8>) {
+        // Four cubic Béziers, each approximating a quarter circle
+        const K: f32 = 0.552_284_8;
+        let (x, y, r) = (cx as f32, self.height - cy as f32, radius as f32);
+        let k = r * K;
+        self.fill(color);
+        self.content
+            .move_to(x + r, y)
+            .cubic_to(x + r, y + k, x + k, y + r, x, y + r)
+            .cubic_to(x - k, y + r, x - r, y + k, x - r, y)
+            .cubic_to(x - r, y - k, x - k, y - r, x, y - r)
+            .cubic_to(x + k, y - r, x + r, y - k, x + r, y)
+            .close_path()
+            .fill_nonzero();
+    }
+}
+
+/// Single-page vector PDF of `lines`, laid out like the PNG: theme
+/// background, span backgrounds, colors and font styles, and the window
+/// frame when `frame` is set. Text uses a subset of the embedded font with
+/// a Unicode map, so it stays selectable and searchable.
+pub fn to_pdf(metrics: &Metrics, lines: &[Line], bg: Rgb<u8>, frame: bool) -> Vec<u8> {
+    let (width, height) = metrics.canvas_size(lines);
+    let (width, height) = (width as f32, height as f32);
+    let (ox, oy, page_width, page_height) = if frame {
+        let bar = metrics.title_bar_height() as f32;
+        (1.0, bar, width + 2.0, height + bar + 1.0)
+    } else {
+        (0.0, 0.0, width, height)
+    };
+
+    let mut canvas = Canvas {
+        content: Content::new(),
+        height: page_height,
+    };
+    canvas
+        .content
+        .transform([PT_PER_PX, 0.0, 0.0, PT_PER_PX, 0.0, 0.0]);
+
+    // Same geometry as `Metrics::window_frame`
+    if frame {
+        canvas.rect(0.0, 0.0, page_width, page_height, shade(bg, 0.6));
+        canvas.rect(1.0, 1.0, width, oy - 1.0, shade(bg, 0.8));
+        for (center, radius, color) in metrics.window_buttons() {
+            canvas.circle(center, radius, color);
+        }
+    }
+    canvas.rect(ox, oy, width, height, bg);
+
+    let font = &metrics.font;
+    let units_per_em = font.units_per_em().unwrap_or(1000.0);
+    let scaled = font.as_scaled(metrics.scale);
+    let font_size = scaled.h_scale_factor() * units_per_em;
+    let ascent = scaled.ascent();
+
+    let mut glyphs: BTreeMap<u16, char> = BTreeMap::new();
+    for (i, line) in lines.iter().enumerate() {
+        let y = oy + metrics.line_y(i as f32) as f32;
+        let mut x = ox + metrics.padding as f32;
+        for (style, text) in line {
+            let text = text.trim_end_matches('\n').trim_end_matches('\r');
+            if text.is_empty() {
+                continue;
+            }
+            let span_width = text.chars().count() as f32 * metrics.char_advance;
+            if style.background.a > 0 {
+                let top = y - (metrics.line_height as f32 - metrics.scale.y) / 2.0;
+                let background = Rgb([style.background.r, style.background.g, style.background.b]);
+                canvas.rect(x, top, span_width, metrics.line_height as f32, background);
+            }
+
+            let color = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
+            let mut encoded = Vec::new();
+            for c in text.chars() {
+                let id = font.glyph_id(c).0;
+                glyphs.entry(id).or_insert(c);
+                encoded.extend(id.to_be_bytes());
+            }
+            canvas.fill(color);
+            let baseline = canvas.height - (y + ascent);
+            let content = &mut canvas.content;
+            content.begin_text();
+            content.set_font(Name(b"F1"), font_size);
+            // The PNG emboldens by overstriking; stroking the outlines matches it
+            if style.font_style.contains(FontStyle::BOLD) {
+                content.set_text_rendering_mode(TextRenderingMode::FillStroke);
+                content.set_line_width(0.6);
+            } else {
+                content.set_text_rendering_mode(TextRenderingMode::Fill);
+            }
+            // Italics are skewed around the same pivot the PNG shears them on
+            if style.font_style.contains(FontStyle::ITALIC) {
+                let shift = ITALIC_SLANT * ITALIC_PIVOT * metrics.scale.y;
+                content.set_text_matrix([1.0, 0.0, ITALIC_SLANT, 1.0, x - shift, baseline]);
+            } else {
+                content.set_text_matrix([1.0, 0.0, 0.0, 1.0, x, baseline]);
+            }
+            content.show(Str(&encoded));
+            content.end_text();
+
+            if style.font_style.contains(FontStyle::UNDERLINE) {
+                canvas.rect(x, y + ascent + 2.0, span_width.max(1.0), 1.0, color);
+            }
+            x += span_width;
+        }
+    }
+
+    write_document(
+        canvas.content.finish(),
+        &glyphs,
+        metrics,
+        page_width * PT_PER_PX,
+        page_height * PT_PER_PX,
+    )
+}
+
+fn write_document(
+    content: Vec<u8>,
+    glyphs: &BTreeMap<u16, char>,
+    metrics: &Metrics,
+    width: f32,
+    height: f32,
+) -> Vec<u8> {
+    let catalog_id = Ref::new(1);
+    let pages_id = Ref::new(2);
+    let page_id = Ref::new(3);
+    let content_id = Ref::new(4);
+    let font_id = Ref::new(5);
+    let cid_font_id = Ref::new(6);
+    let descriptor_id = Ref::new(7);
+    let font_file_id = Ref::new(8);
+    let cmap_id = Ref::new(9);
+
+    let mut pdf = Pdf::new();
+    pdf.catalog(catalog_id).pages(pages_id);
+    pdf.pages(pages_id).kids([page_id]).count(1);
+    let mut page = pdf.page(page_id);
+    page.media_box(Rect::new(0.0, 0.0, width, height))
+        .parent(pages_id)
+        .contents(content_id);
+    page.resources().fonts().pair(Name(b"F1"), font_id);
+    page.finish();
+    pdf.stream(content_id, &content);
+
+    // Glyph space is 1000 units per em
+    let font = &metrics.font;
+    let units_per_em = font.units_per_em().unwrap_or(1000.0);
+    let to_glyph_space = |v: f32| v * 1000.0 / units_per_em;
+    let advance = to_glyph_space(font.h_advance_unscaled(font.glyph_id('M')));
+    let ascent = to_glyph_space(font.ascent_unscaled());
+    let descent = to_glyph_space(font.descent_unscaled());
+
+    pdf.type0_font(font_id)
+        .base_font(FONT_NAME)
+        .encoding_predefined(Name(b"Identity-H"))
+        .descendant_font(cid_font_id)
+        .to_unicode(cmap_id);
+
+    let mut cid_font = pdf.cid_font(cid_font_id);
+    cid_font
+        .subtype(CidFontType::Type2)
+        .base_font(FONT_NAME)
+        .system_info(SYSTEM_INFO)
+        .font_descriptor(descriptor_id)
+        .default_width(advance)
+        .cid_to_gid_map_predefined(Name(b"Identity"));
+    let mut widths = cid_font.widths();
+    for &id in glyphs.keys() {
+        let width = to_glyph_space(font.h_advance_unscaled(GlyphId(id)));
+        widths.consecutive(id, [width]);
+    }
+    widths.finish();
+    cid_font.finish();
+
+    pdf.font_descriptor(descriptor_id)
+        .name(FONT_NAME)
+        .flags(FontFlags::FIXED_PITCH | FontFlags::NON_SYMBOLIC)
+        .bbox(Rect::new(0.0, descent, advance, ascent))
+        .italic_angle(0.0)
+        .ascent(ascent)
+        .descent(descent)
+        .cap_height(ascent)
+        .stem_v(80.0)
+        .font_file2(font_file_id);
+
+    // Subsetting keeps glyph IDs, so they double as CIDs
+    let ids: Vec<u16> = glyphs.keys().copied().collect();
+    let subset = subsetter::subset(FONT_DATA, 0, subsetter::Profile::pdf(&ids))
+        .expect("Failed to subset font");
+    pdf.stream(font_file_id, &subset)
+        .pair(Name(b"Length1"), subset.len() as i32);
+
+    let mut cmap = UnicodeCmap::new(Name(b"Custom"), SYSTEM_INFO);
+    for (&id, &c) in glyphs {
+        cmap.pair(id, c);
+    }
+    pdf.cmap(cmap_id, &cmap.finish());
+
+    pdf.finish()
+}
@@ -0,0 +1,57 @@
+use crate::highlight;
+use crate::render::{self, plain_style, Line, Metrics};
+use image::{Rgb, RgbImage};
+use syntect::highlighting::ThemeSet;
+use syntect::parsing::SyntaxSet;
+
+/// Snippet shown in every tile of the contact sheet; short, but touching
+/// comments, keywords, strings, numbers, types and macros.
+const SAMPLE: &str = r#"// Count words in a line
+fn count(line: &str) -> usize {
+    let words = line.split_whitespace();
+    println!("{} words", words.clone().count());
+    words.filter(|w| w.len() > 3).count() + 0
+}
+"#;
+
+/// Space between tiles and around the sheet.
+const GAP: u32 = 24;
+
+/// Render `SAMPLE` in every theme of `ts`, each tile headed by the theme's
+/// name, laid out in a grid `columns` tiles wide.
+pub fn contact_sheet(ts: &ThemeSet, ss: &SyntaxSet, font_size: f32, columns: usize) -> RgbImage {
+    let metrics = Metrics::new(font_size);
+    let syntax = ss
+        .find_syntax_by_token("rust")
+        .unwrap_or_else(|| ss.find_syntax_plain_text());
+
+    let tiles: Vec<RgbImage> = ts
+        .themes
+        .iter()
+        .map(|(name, theme)| {
+            let (bg, fg) = (render::background(theme), render::foreground(theme));
+            let mut lines: Vec<Line> = vec![vec![(plain_style(fg, true), name.clone())], vec![]];
+            lines.extend(highlight(SAMPLE, syntax, theme, ss));
+            let (width, height) = metrics.canvas_size(&lines);
+            let mut tile = RgbImage::from_pixel(width, height, bg);
+            metrics.draw_lines(&mut tile, &lines, None);
+            tile
+        })
+        .collect();
+
+    let columns = columns.clamp(1, tiles.len().max(1));
+    let rows = tiles.len().div_ceil(columns);
+    let tile_width = tiles.iter().map(|t| t.width()).max().unwrap_or(0);
+    let tile_height = tiles.iter().map(|t| t.height()).max().unwrap_or(0);
+    let mut sheet = RgbImage::from_pixel(
+        GAP + columns as u32 * (tile_width + GAP),
+        GAP + rows as u32 * (tile_height + GAP),
+        Rgb([128, 128, 128]),
+    );
+    for (i, tile) in tiles.iter().enumerate() {
+        let x = GAP + (i % columns) as u32 * (tile_width + GAP);
+        let y = GAP + (i / columns) as u32 * (tile_height + GAP);
+        image::imageops::replace(&mut sheet, tile, x as i64, y as i64);
+    }
+    sheet
+}
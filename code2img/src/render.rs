@@ -0,0 +1,376 @@
+use ab_glyph::{point, Font, FontRef, PxScale, ScaleFont};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{draw_filled_circle_mut, draw_filled_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use syntect::highlighting::{Color, FontStyle, Highlighter, Style, Theme};
+use syntect::parsing::Scope;
+
+/// One highlighted source line as styled spans. A span's background is only
+/// painted when its alpha is non-zero; otherwise the canvas shows through.
+pub type Line = Vec<(Style, String)>;
+
+/// Embedded monospace font.
+pub const FONT_DATA: &[u8] = include_bytes!("../assets/JetBrainsMono-Regular.ttf");
+
+/// Horizontal slant of synthesized italics, as x offset per pixel of height.
+pub const ITALIC_SLANT: f32 = 0.2;
+
+/// Height above the baseline, as a fraction of the font size, that italic
+/// shears pivot around: about the middle of lowercase letters.
+pub const ITALIC_PIVOT: f32 = 0.27;
+
+/// Colors of the close, minimize and zoom buttons in the window frame.
+const WINDOW_BUTTONS: [Rgb<u8>; 3] = [Rgb([255, 95, 86]), Rgb([255, 189, 46]), Rgb([39, 201, 63])];
+
+/// Font and spacing shared by every output mode, so static and animated
+/// renders of the same input lay out identically.
+pub struct Metrics {
+    pub font: FontRef<'static>,
+    pub scale: PxScale,
+    pub char_advance: f32,
+    pub line_height: u32,
+    pub padding: u32,
+}
+
+impl Metrics {
+    pub fn new(font_size: f32) -> Self {
+        let font = FontRef::try_from_slice(FONT_DATA).expect("Failed to load font");
+        let scale = PxScale::from(font_size);
+
+        // Get the actual advance width of a monospace character using 'M'
+        let char_advance = font.as_scaled(scale).h_advance(font.glyph_id('M'));
+
+        Metrics {
+            font,
+            scale,
+            char_advance,
+            line_height: (font_size * 1.5) as u32,
+            padding: 32,
+        }
+    }
+
+    /// Image size needed to hold `lines` with padding on every side.
+    pub fn canvas_size(&self, lines: &[Line]) -> (u32, u32) {
+        let max_line_len = lines.iter().map(line_chars).max().unwrap_or(0);
+        let width =
+            (max_line_len as f32 * self.char_advance + self.padding as f32 * 2.0).max(400.0);
+        let height = (lines.len() as u32 * self.line_height + self.padding * 2).max(100);
+        (width as u32, height)
+    }
+
+    /// Draw `lines` onto `img`. When `reveal` is set, only that many characters
+    /// (not counting line endings) are drawn. Returns the pixel position just
+    /// after the last drawn character, which is where a text cursor belongs.
+    pub fn draw_lines(
+        &self,
+        img: &mut RgbImage,
+        lines: &[Line],
+        reveal: Option<usize>,
+    ) -> (i32, i32) {
+        let mut remaining = reveal.unwrap_or(usize::MAX);
+        let mut cursor = (self.padding as i32, self.padding as i32);
+
+        for (line_idx, spans) in lines.iter().enumerate() {
+            if remaining == 0 {
+                break;
+            }
+            let y = self.line_y(line_idx as f32);
+            let (x, drawn) = self.draw_line(img, spans, y, remaining);
+            remaining -= drawn;
+            cursor = (x, y);
+        }
+        cursor
+    }
+
+    /// Top of the text row for `line_idx`; fractional indices are allowed so
+    /// animations can place lines between rows.
+    pub fn line_y(&self, line_idx: f32) -> i32 {
+        self.padding as i32 + (line_idx * self.line_height as f32).round() as i32
+    }
+
+    /// Draw at most `limit` characters of a single line with its top at `y`.
+    /// Returns the x position after the last drawn character and how many
+    /// characters were drawn.
+    pub fn draw_line(
+        &self,
+        img: &mut RgbImage,
+        spans: &Line,
+        y: i32,
+        limit: usize,
+    ) -> (i32, usize) {
+        let mut x_offset: f32 = self.padding as f32;
+        let mut drawn = 0;
+
+        for (style, text) in spans {
+            if drawn == limit {
+                break;
+            }
+            let color = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
+            let draw_text_str = text.trim_end_matches('\n').trim_end_matches('\r');
+            let draw_text_str = match draw_text_str.char_indices().nth(limit - drawn) {
+                Some((end, _)) => &draw_text_str[..end],
+                None => draw_text_str,
+            };
+            if !draw_text_str.is_empty() {
+                let count = draw_text_str.chars().count();
+                let span_width = count as f32 * self.char_advance;
+                let x = x_offset as i32;
+                if style.background.a > 0 {
+                    let bg = Rgb([style.background.r, style.background.g, style.background.b]);
+                    let top = y - ((self.line_height as f32 - self.scale.y) / 2.0) as i32;
+                    let right = (x_offset + span_width) as i32;
+                    let rect =
+                        Rect::at(x, top).of_size((right - x).max(1) as u32, self.line_height);
+                    draw_filled_rect_mut(img, rect, bg);
+                }
+                // The embedded font has a single face, so embolden by
+                // overstriking and slant italics by shearing the outlines
+                let strikes = if style.font_style.contains(FontStyle::BOLD) {
+                    2
+                } else {
+                    1
+                };
+                for dx in 0..strikes {
+                    if style.font_style.contains(FontStyle::ITALIC) {
+                        self.draw_italic(img, color, (x + dx, y), draw_text_str);
+                    } else {
+                        draw_text_mut(img, color, x + dx, y, self.scale, &self.font, draw_text_str);
+                    }
+                }
+                if style.font_style.contains(FontStyle::UNDERLINE) {
+                    let underline_y = y as f32 + self.font.as_scaled(self.scale).ascent() + 2.0;
+                    let rect =
+                        Rect::at(x, underline_y as i32).of_size(span_width.max(1.0) as u32, 1);
+                    draw_filled_rect_mut(img, rect, color);
+                }
+                // Advance by actual glyph width * character count
+                x_offset += span_width;
+                drawn += count;
+            }
+        }
+        (x_offset as i32, drawn)
+    }
+
+    /// Draw `text` with its top at `y` like `draw_text_mut`, but sheared by
+    /// `ITALIC_SLANT` around the middle of lowercase letters.
+    fn draw_italic(&self, img: &mut RgbImage, color: Rgb<u8>, (x, y): (i32, i32), text: &str) {
+        let font = self.font.as_scaled(self.scale);
+        let baseline = y as f32 + font.ascent();
+        let pivot = baseline - self.scale.y * ITALIC_PIVOT;
+        let mut pen_x = x as f32;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = font.glyph_id(c);
+            if let Some(previous) = previous {
+                pen_x += font.kern(previous, id);
+            }
+            previous = Some(id);
+            let glyph = id.with_scale_and_position(self.scale, point(pen_x, baseline));
+            pen_x += font.h_advance(id);
+            let Some(outlined) = self.font.outline_glyph(glyph) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            outlined.draw(|gx, gy, coverage| {
+                let py = bounds.min.y + gy as f32;
+                let px = bounds.min.x + gx as f32 + (pivot - py) * ITALIC_SLANT;
+                let (px, py) = (px.round() as i32, py as i32);
+                if px < 0 || py < 0 || px >= img.width() as i32 || py >= img.height() as i32 {
+                    return;
+                }
+                let under = img.get_pixel_mut(px as u32, py as u32);
+                let mix =
+                    |f: u8, b: u8| (b as f32 + (f as f32 - b as f32) * coverage).round() as u8;
+                *under = Rgb([
+                    mix(color[0], under[0]),
+                    mix(color[1], under[1]),
+                    mix(color[2], under[2]),
+                ]);
+            });
+        }
+    }
+
+    /// X position where character `col` of `spans` starts, following the same
+    /// per-span advance as `draw_line`.
+    pub fn char_x(&self, spans: &Line, col: usize) -> f32 {
+        let mut x_offset = self.padding as f32;
+        let mut remaining = col;
+        for (_, text) in spans {
+            let count = text
+                .trim_end_matches('\n')
+                .trim_end_matches('\r')
+                .chars()
+                .count();
+            let take = count.min(remaining);
+            x_offset += take as f32 * self.char_advance;
+            remaining -= take;
+        }
+        // Columns past the end of the line continue at the same pitch
+        x_offset + remaining as f32 * self.char_advance
+    }
+
+    /// Draw a small page indicator such as "1/3" in the bottom-right padding.
+    pub fn draw_page_number(&self, img: &mut RgbImage, text: &str, color: Rgb<u8>) {
+        let scale = PxScale::from(self.scale.y * 0.6);
+        let advance = self
+            .font
+            .as_scaled(scale)
+            .h_advance(self.font.glyph_id('M'));
+        let width = text.chars().count() as f32 * advance;
+        let x = img.width() as f32 - self.padding as f32 / 2.0 - width;
+        let y = img.height() as f32 - (self.padding as f32 + scale.y) / 2.0;
+        draw_text_mut(img, color, x as i32, y as i32, scale, &self.font, text);
+    }
+
+    /// Wrap `img` in terminal-style window chrome: a title bar with the three
+    /// window buttons and a thin border, both shaded from `bg`.
+    pub fn window_frame(&self, img: &RgbImage, bg: Rgb<u8>) -> RgbImage {
+        let bar_height = self.title_bar_height();
+        let (width, height) = img.dimensions();
+        let mut framed = RgbImage::from_pixel(width + 2, height + bar_height + 1, shade(bg, 0.6));
+        let bar = Rect::at(1, 1).of_size(width, bar_height - 1);
+        draw_filled_rect_mut(&mut framed, bar, shade(bg, 0.8));
+        image::imageops::replace(&mut framed, img, 1, bar_height as i64);
+
+        for (center, radius, color) in self.window_buttons() {
+            draw_filled_circle_mut(&mut framed, center, radius, color);
+        }
+        framed
+    }
+
+    /// Height of the title bar `window_frame` adds above the image.
+    pub fn title_bar_height(&self) -> u32 {
+        self.line_height + self.line_height / 4
+    }
+
+    /// Center, radius and color of each title bar button.
+    pub fn window_buttons(&self) -> Vec<((i32, i32), i32, Rgb<u8>)> {
+        let radius = (self.line_height / 6).max(3) as i32;
+        WINDOW_BUTTONS
+            .into_iter()
+            .enumerate()
+            .map(|(i, color)| {
+                let center = (
+                    self.padding as i32 / 2 + radius + i as i32 * radius * 3,
+                    self.title_bar_height() as i32 / 2,
+                );
+                (center, radius, color)
+            })
+            .collect()
+    }
+
+    /// Draw a block cursor whose top-left corner is at `pos`.
+    pub fn draw_cursor(&self, img: &mut RgbImage, pos: (i32, i32), color: Rgb<u8>) {
+        let width = (self.char_advance * 0.6).max(2.0) as u32;
+        let rect = Rect::at(pos.0, pos.1).of_size(width, self.scale.y as u32);
+        draw_filled_rect_mut(img, rect, color);
+    }
+}
+
+/// Number of visible characters in a line, excluding its line ending.
+pub fn line_chars(line: &Line) -> usize {
+    line.iter()
+        .map(|(_, text)| {
+            text.trim_end_matches('\n')
+                .trim_end_matches('\r')
+                .chars()
+                .count()
+        })
+        .sum()
+}
+
+/// Text of a line without its styling or line ending.
+pub fn line_text(line: &Line) -> String {
+    line.iter()
+        .map(|(_, text)| text.trim_end_matches('\n').trim_end_matches('\r'))
+        .collect()
+}
+
+/// A copy of `line` with every foreground blended toward `bg`, for fading
+/// lines in and out. `opacity` 1.0 leaves the colors unchanged.
+pub fn faded(line: &Line, bg: Rgb<u8>, opacity: f32) -> Line {
+    line.iter()
+        .map(|(style, text)| {
+            let mut style = *style;
+            let fg = Rgb([style.foreground.r, style.foreground.g, style.foreground.b]);
+            let Rgb([r, g, b]) = blend(fg, bg, opacity);
+            (style.foreground.r, style.foreground.g, style.foreground.b) = (r, g, b);
+            (style, text.clone())
+        })
+        .collect()
+}
+
+/// Mix of `fg` over `bg`; `opacity` 1.0 is pure `fg`.
+pub fn blend(fg: Rgb<u8>, bg: Rgb<u8>, opacity: f32) -> Rgb<u8> {
+    let mix = |f: u8, b: u8| (b as f32 + (f as f32 - b as f32) * opacity).round() as u8;
+    Rgb([mix(fg[0], bg[0]), mix(fg[1], bg[1]), mix(fg[2], bg[2])])
+}
+
+/// Untinted span style in `color`, for text the tool adds itself (headers,
+/// labels) rather than text from the highlighter.
+pub fn plain_style(color: Rgb<u8>, bold: bool) -> Style {
+    Style {
+        foreground: Color {
+            r: color[0],
+            g: color[1],
+            b: color[2],
+            a: 255,
+        },
+        background: Color {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        },
+        font_style: if bold {
+            FontStyle::BOLD
+        } else {
+            FontStyle::empty()
+        },
+    }
+}
+
+/// Scope-derived style for `scope` in `theme`, with the background left
+/// for the canvas to provide.
+pub fn scope_style(theme: &Theme, scope: &str) -> Style {
+    let scope = Scope::new(scope).expect("valid scope");
+    theme_colors(Highlighter::new(theme).style_for_stack(&[scope]))
+}
+
+/// A theme's `style` as highlighted code is drawn: its foreground color
+/// only. The canvas provides the background, and theme bold, italic and
+/// underline are dropped so themes render as they always have; font styles
+/// come from terminal attributes (`--ansi`, `--cast`) and the tool's own
+/// labels.
+pub fn theme_colors(mut style: Style) -> Style {
+    style.background.a = 0;
+    style.font_style = FontStyle::empty();
+    style
+}
+
+/// `color` scaled toward black by `factor`.
+pub fn shade(color: Rgb<u8>, factor: f32) -> Rgb<u8> {
+    Rgb(color.0.map(|c| (c as f32 * factor).round() as u8))
+}
+
+/// Background color from theme
+pub fn background(theme: &Theme) -> Rgb<u8> {
+    let bg = theme.settings.background.unwrap_or(Color {
+        r: 43,
+        g: 48,
+        b: 59,
+        a: 255,
+    });
+    Rgb([bg.r, bg.g, bg.b])
+}
+
+/// Foreground color from theme, used for cursors and other chrome.
+pub fn foreground(theme: &Theme) -> Rgb<u8> {
+    let fg = theme.settings.foreground.unwrap_or(Color {
+        r: 192,
+        g: 197,
+        b: 206,
+        a: 255,
+    });
+    Rgb([fg.r, fg.g, fg.b])
+}
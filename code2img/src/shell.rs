@@ -0,0 +1,109 @@
+use crate::render::{scope_style, theme_colors, Line};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+
+/// Prompts that mark a command line, as used in `console` code fences.
+const PROMPTS: &[&str] = &["$ ", "# ", "> "];
+
+/// Highlight a shell session transcript: lines starting with a prompt are
+/// commands highlighted as bash, with the prompt glyph styled separately;
+/// everything else is command output and drawn in a muted color. A command
+/// ending in `\` continues onto the next line.
+pub fn highlight_session(text: &str, ss: &SyntaxSet, theme: &Theme) -> Vec<Line> {
+    let bash = ss
+        .find_syntax_by_token("bash")
+        .unwrap_or_else(|| ss.find_syntax_plain_text());
+    let mut h = HighlightLines::new(bash, theme);
+
+    let mut prompt_style = scope_style(theme, "keyword");
+    prompt_style.font_style |= FontStyle::BOLD;
+    let output_style = scope_style(theme, "comment");
+
+    let mut lines = Vec::new();
+    let mut continued = false;
+    for line in LinesWithEndings::from(text) {
+        let prompt = PROMPTS
+            .iter()
+            .find(|p| line.starts_with(**p) || line.trim_end() == p.trim_end());
+        let (prefix, command) = match prompt {
+            Some(p) => line.split_at(p.len().min(line.trim_end().len())),
+            None if continued => ("", line),
+            None => {
+                lines.push(vec![(output_style, line.to_string())]);
+                continue;
+            }
+        };
+
+        let mut styled: Line = Vec::new();
+        if !prefix.is_empty() {
+            styled.push((prompt_style, prefix.to_string()));
+        }
+        let ranges = h.highlight_line(command, ss).unwrap();
+        styled.extend(
+            ranges
+                .into_iter()
+                .map(|(style, text)| (theme_colors(style), text.to_string())),
+        );
+        lines.push(styled);
+        continued = command.trim_end().ends_with('\\');
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+
+    fn session(text: &str) -> (Vec<Line>, Theme) {
+        let theme = ThemeSet::load_defaults().themes["base16-ocean.dark"].clone();
+        let lines = highlight_session(text, &SyntaxSet::load_defaults_newlines(), &theme);
+        (lines, theme)
+    }
+
+    fn text(line: &Line) -> String {
+        line.iter().map(|(_, t)| t.as_str()).collect()
+    }
+
+    /// Whether `line` is drawn as command output: one muted span.
+    fn is_output(line: &Line, theme: &Theme) -> bool {
+        line.len() == 1 && line[0].0 == scope_style(theme, "comment")
+    }
+
+    #[test]
+    fn prompts_and_output() {
+        let (lines, theme) = session("$ ls -l\ntotal 0\n# whoami\nroot\n> exit\n");
+        assert_eq!(lines.len(), 5);
+        for (i, prompt) in [(0, "$ "), (2, "# "), (4, "> ")] {
+            let (style, span) = &lines[i][0];
+            assert_eq!(span, prompt);
+            assert!(style.font_style.contains(FontStyle::BOLD));
+            assert!(!is_output(&lines[i], &theme));
+        }
+        assert_eq!(text(&lines[0]), "$ ls -l\n");
+        assert!(is_output(&lines[1], &theme));
+        assert!(is_output(&lines[3], &theme));
+    }
+
+    #[test]
+    fn bare_prompts() {
+        let (lines, theme) = session("$\n#\n#!/bin/sh\n$5 off\n");
+        assert_eq!(lines[0][0].1, "$");
+        assert_eq!(lines[1][0].1, "#");
+        assert!(!is_output(&lines[0], &theme));
+        assert!(!is_output(&lines[1], &theme));
+        // A prompt needs a space after it or nothing at all
+        assert!(is_output(&lines[2], &theme));
+        assert!(is_output(&lines[3], &theme));
+    }
+
+    #[test]
+    fn continued_commands() {
+        let (lines, theme) = session("$ cargo build \\\n    --release\n   Compiling app\n");
+        assert_eq!(text(&lines[1]), "    --release\n");
+        assert!(!is_output(&lines[1], &theme));
+        assert!(is_output(&lines[2], &theme));
+    }
+}
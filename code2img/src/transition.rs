@@ -0,0 +1,112 @@
+use crate::anim::AnimFrame;
+use crate::render::{faded, line_text, Line, Metrics};
+use image::{Rgb, RgbImage};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// Delay between in-between frames of a transition.
+const FRAME_MS: u32 = 40;
+
+/// Where a line sits before and after a transition, as fractional row indices.
+enum Motion<'a> {
+    /// Unchanged line sliding from one row to another.
+    Move(&'a Line, f32, f32),
+    /// Line only in the old revision, fading out in place.
+    Remove(&'a Line, f32),
+    /// Line only in the new revision, fading in at its final row.
+    Add(&'a Line, f32),
+}
+
+/// Frames that hold on each revision in `steps` and animate between
+/// consecutive ones: unchanged lines slide to their new rows, removed lines
+/// fade out and added lines fade in. Every frame is sized to fit the largest
+/// revision.
+pub fn transition_frames(
+    metrics: &Metrics,
+    steps: &[Vec<Line>],
+    bg: Rgb<u8>,
+    transition_ms: u32,
+    hold_ms: u32,
+) -> Vec<AnimFrame> {
+    let (width, height) = steps
+        .iter()
+        .map(|lines| metrics.canvas_size(lines))
+        .fold((0, 0), |(w, h), (lw, lh)| (w.max(lw), h.max(lh)));
+
+    let still = |lines: &[Line]| {
+        let mut img = RgbImage::from_pixel(width, height, bg);
+        metrics.draw_lines(&mut img, lines, None);
+        img
+    };
+
+    let frame_count = (transition_ms / FRAME_MS).max(1);
+    let mut frames = Vec::new();
+    for pair in steps.windows(2) {
+        frames.push(AnimFrame {
+            image: still(&pair[0]),
+            delay_ms: hold_ms.max(FRAME_MS),
+        });
+        let motions = diff_lines(&pair[0], &pair[1]);
+        for i in 1..frame_count {
+            let t = ease_in_out(i as f32 / frame_count as f32);
+            let mut img = RgbImage::from_pixel(width, height, bg);
+            for motion in &motions {
+                match *motion {
+                    Motion::Move(line, from, to) => {
+                        let y = metrics.line_y(from + (to - from) * t);
+                        metrics.draw_line(&mut img, line, y, usize::MAX);
+                    }
+                    Motion::Remove(line, row) => {
+                        let y = metrics.line_y(row);
+                        metrics.draw_line(&mut img, &faded(line, bg, 1.0 - t), y, usize::MAX);
+                    }
+                    Motion::Add(line, row) => {
+                        let y = metrics.line_y(row);
+                        metrics.draw_line(&mut img, &faded(line, bg, t), y, usize::MAX);
+                    }
+                }
+            }
+            frames.push(AnimFrame {
+                image: img,
+                delay_ms: FRAME_MS,
+            });
+        }
+    }
+    if let Some(last) = steps.last() {
+        frames.push(AnimFrame {
+            image: still(last),
+            delay_ms: hold_ms.max(FRAME_MS),
+        });
+    }
+    frames
+}
+
+/// Line-level diff of two revisions, matching lines by their text.
+fn diff_lines<'a>(old: &'a [Line], new: &'a [Line]) -> Vec<Motion<'a>> {
+    let old_text: Vec<String> = old.iter().map(line_text).collect();
+    let new_text: Vec<String> = new.iter().map(line_text).collect();
+
+    let mut motions = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, &old_text, &new_text) {
+        let (tag, old_range, new_range) = op.as_tag_tuple();
+        match tag {
+            DiffTag::Equal => {
+                for (o, n) in old_range.zip(new_range) {
+                    motions.push(Motion::Move(&new[n], o as f32, n as f32));
+                }
+            }
+            DiffTag::Delete | DiffTag::Insert | DiffTag::Replace => {
+                motions.extend(old_range.map(|o| Motion::Remove(&old[o], o as f32)));
+                motions.extend(new_range.map(|n| Motion::Add(&new[n], n as f32)));
+            }
+        }
+    }
+    motions
+}
+
+fn ease_in_out(t: f32) -> f32 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    } else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
@@ -0,0 +1,257 @@
+use crate::render::{scope_style, theme_colors, Line};
+use regex::Regex;
+use std::cmp::Reverse;
+use streaming_iterator::StreamingIterator;
+use syntect::highlighting::{Highlighter, Style, Theme};
+use syntect::util::LinesWithEndings;
+use tree_sitter::{Language, Parser, Query, QueryCursor};
+
+/// Component tags such as `<Foo>`, which the bundled JSX query leaves to
+/// the uppercase `@constructor` rule; they are tags, as syntect draws them.
+const JSX_COMPONENTS_QUERY: &str = "
+(jsx_opening_element name: (identifier) @tag)
+(jsx_closing_element name: (identifier) @tag)
+(jsx_self_closing_element name: (identifier) @tag)
+";
+
+/// Bundled grammar and highlight query for a `-l` token, or `None` when
+/// there is no tree-sitter grammar for it.
+fn grammar(lang: &str) -> Option<(Language, String)> {
+    let js = tree_sitter_javascript::HIGHLIGHT_QUERY;
+    let jsx = tree_sitter_javascript::JSX_HIGHLIGHT_QUERY;
+    let ts = tree_sitter_typescript::HIGHLIGHTS_QUERY;
+    let components = JSX_COMPONENTS_QUERY;
+    let (language, queries): (Language, &[&str]) = match lang.to_lowercase().as_str() {
+        "rust" | "rs" => (
+            tree_sitter_rust::LANGUAGE.into(),
+            &[tree_sitter_rust::HIGHLIGHTS_QUERY],
+        ),
+        "typescript" | "ts" | "mts" | "cts" => (
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            &[js, ts],
+        ),
+        "tsx" => (
+            tree_sitter_typescript::LANGUAGE_TSX.into(),
+            &[js, ts, jsx, components],
+        ),
+        "javascript" | "js" | "jsx" | "mjs" | "cjs" => (
+            tree_sitter_javascript::LANGUAGE.into(),
+            &[js, jsx, components],
+        ),
+        "python" | "py" => (
+            tree_sitter_python::LANGUAGE.into(),
+            &[tree_sitter_python::HIGHLIGHTS_QUERY],
+        ),
+        "go" | "golang" => (
+            tree_sitter_go::LANGUAGE.into(),
+            &[tree_sitter_go::HIGHLIGHTS_QUERY],
+        ),
+        "json" => (
+            tree_sitter_json::LANGUAGE.into(),
+            &[tree_sitter_json::HIGHLIGHTS_QUERY],
+        ),
+        "bash" | "sh" | "shell" | "zsh" => (
+            tree_sitter_bash::LANGUAGE.into(),
+            &[tree_sitter_bash::HIGHLIGHT_QUERY],
+        ),
+        _ => return None,
+    };
+    Some((language, queries.join("\n")))
+}
+
+/// Whether a query pattern captures a node on its own, such as
+/// `(identifier) @variable` or `[(true) (false)] @constant`, rather than
+/// in context, such as `(call_expression function: (identifier) @function)`.
+fn is_bare(pattern: &str) -> bool {
+    let bare = Regex::new(r#"^\(*(\(\w+\)|"[^"]*"|\[[^\]]*\])\s*@"#).expect("valid regex");
+    bare.is_match(pattern.trim_start())
+}
+
+/// TextMate scope standing in for a tree-sitter capture name, so syntect
+/// themes apply unchanged. `None` leaves the text in the default color.
+fn capture_scope(capture: &str) -> Option<&'static str> {
+    // Try the full name first, then drop trailing components:
+    // `function.method` falls back to `function`
+    let mut name = capture;
+    loop {
+        let scope = match name {
+            "keyword" => Some("keyword"),
+            "function" => Some("entity.name.function"),
+            "function.builtin" => Some("support.function"),
+            "function.macro" => Some("support.macro"),
+            "type" | "constructor" => Some("entity.name.type"),
+            "type.builtin" => Some("storage.type"),
+            "string" => Some("string"),
+            "string.special.key" => Some("support.type.property-name"),
+            "escape" => Some("constant.character.escape"),
+            "number" => Some("constant.numeric"),
+            "constant" => Some("constant.other"),
+            "constant.builtin" => Some("constant.language"),
+            "comment" => Some("comment"),
+            "variable.builtin" => Some("variable.language"),
+            "variable.parameter" => Some("variable.parameter"),
+            "property" => Some("variable.other.member"),
+            "operator" => Some("keyword.operator"),
+            "punctuation" => Some("punctuation"),
+            "punctuation.special" => Some("punctuation.section.embedded"),
+            "tag" => Some("entity.name.tag"),
+            "attribute" => Some("entity.other.attribute-name"),
+            "label" => Some("entity.name.label"),
+            // Plain identifiers and embedded code keep the default color
+            "variable" | "embedded" => None,
+            _ => match name.rsplit_once('.') {
+                Some((parent, _)) => {
+                    name = parent;
+                    continue;
+                }
+                None => None,
+            },
+        };
+        return scope;
+    }
+}
+
+/// Highlight `code` with the bundled tree-sitter grammar for `lang`, styled
+/// by `theme`. Returns `None` when no grammar exists for `lang`, so the
+/// caller can fall back to syntect.
+pub fn highlight(code: &str, lang: &str, theme: &Theme) -> Option<Vec<Line>> {
+    let (language, source) = grammar(lang)?;
+    let query = Query::new(&language, &source).expect("Failed to compile highlight query");
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language)
+        .expect("Failed to load tree-sitter grammar");
+    let tree = parser.parse(code, None)?;
+
+    let default = theme_colors(Highlighter::new(theme).get_default());
+    let styles: Vec<Style> = query
+        .capture_names()
+        .iter()
+        .map(|name| capture_scope(name).map_or(default, |scope| scope_style(theme, scope)))
+        .collect();
+
+    // Paint captures from the outermost node inwards so nested nodes win.
+    // For the same node, a pattern that matches it in context beats one
+    // that matches it on its own, and otherwise the later pattern wins.
+    // Grammars disagree on whether catch-alls such as `(identifier)
+    // @variable` open or close their query, and this reads both right.
+    let bare: Vec<bool> = (0..query.pattern_count())
+        .map(|i| is_bare(&source[query.start_byte_for_pattern(i)..query.end_byte_for_pattern(i)]))
+        .collect();
+    let mut captures = Vec::new();
+    let mut cursor = QueryCursor::new();
+    let mut matches = cursor.matches(&query, tree.root_node(), code.as_bytes());
+    while let Some(m) = matches.next() {
+        for capture in m.captures {
+            let range = capture.node.byte_range();
+            let rank = (!bare[m.pattern_index], m.pattern_index);
+            captures.push((range, rank, capture.index as usize));
+        }
+    }
+    captures.sort_by_key(|(range, rank, _)| (Reverse(range.len()), *rank));
+    let mut byte_styles = vec![default; code.len()];
+    for (range, _, capture) in captures {
+        byte_styles[range].fill(styles[capture]);
+    }
+
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for text in LinesWithEndings::from(code) {
+        let mut line: Line = Vec::new();
+        for (i, ch) in text.char_indices() {
+            let style = byte_styles[offset + i];
+            match line.last_mut() {
+                Some((last, span)) if *last == style => span.push(ch),
+                _ => line.push((style, ch.to_string())),
+            }
+        }
+        offset += text.len();
+        lines.push(line);
+    }
+    Some(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syntect::highlighting::ThemeSet;
+
+    /// Style of the first span of `lines` containing `text`.
+    fn style_of(lines: &[Line], text: &str) -> Style {
+        lines
+            .iter()
+            .flatten()
+            .find(|(_, span)| span.contains(text))
+            .map(|(style, _)| *style)
+            .unwrap_or_else(|| panic!("no span '{}'", text))
+    }
+
+    #[test]
+    fn specific_patterns_beat_catch_alls() {
+        let theme = &ThemeSet::load_defaults().themes["base16-ocean.dark"];
+        let code =
+            "function add(a, b) {\n  return a + b;\n}\nconst el = <div><Foo bar=\"1\" /></div>;\n";
+        let lines = highlight(code, "jsx", theme).unwrap();
+        assert_eq!(
+            style_of(&lines, "add"),
+            scope_style(theme, "entity.name.function")
+        );
+        assert_eq!(
+            style_of(&lines, "div"),
+            scope_style(theme, "entity.name.tag")
+        );
+        assert_eq!(
+            style_of(&lines, "Foo"),
+            scope_style(theme, "entity.name.tag")
+        );
+        assert_eq!(
+            style_of(&lines, "bar"),
+            scope_style(theme, "entity.other.attribute-name")
+        );
+    }
+
+    #[test]
+    fn tsx_component_tags() {
+        let theme = &ThemeSet::load_defaults().themes["base16-ocean.dark"];
+        let code = "function App(props: Props) {\n  return <Foo bar={props.x} />;\n}\n";
+        let lines = highlight(code, "tsx", theme).unwrap();
+        assert_eq!(
+            style_of(&lines, "App"),
+            scope_style(theme, "entity.name.function")
+        );
+        assert_eq!(
+            style_of(&lines, "Foo"),
+            scope_style(theme, "entity.name.tag")
+        );
+    }
+
+    #[test]
+    fn bare_patterns() {
+        assert!(is_bare("(identifier) @variable"));
+        assert!(is_bare(
+            "((identifier) @constructor\n (#match? @constructor \"^[A-Z]\"))"
+        ));
+        assert!(is_bare("[\n  (true)\n  (false)\n] @constant.builtin"));
+        assert!(is_bare("\"<\" @punctuation.bracket"));
+        assert!(!is_bare(
+            "(call_expression\n  function: (identifier) @function)"
+        ));
+        assert!(!is_bare("(pair\n  key: (_) @string.special.key)"));
+    }
+
+    #[test]
+    fn catch_alls_at_the_end_of_a_query() {
+        let theme = &ThemeSet::load_defaults().themes["base16-ocean.dark"];
+        let lines = highlight("func add(a int) int {\n\treturn a\n}\n", "go", theme).unwrap();
+        assert_eq!(
+            style_of(&lines, "add"),
+            scope_style(theme, "entity.name.function")
+        );
+        let lines = highlight("{\"key\": \"value\"}", "json", theme).unwrap();
+        assert_eq!(
+            style_of(&lines, "\"key\""),
+            scope_style(theme, "support.type.property-name")
+        );
+        assert_eq!(style_of(&lines, "\"value\""), scope_style(theme, "string"));
+    }
+}
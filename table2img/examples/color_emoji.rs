@@ -0,0 +1,864 @@
+//! Builds `assets/ColorEmoji.ttf`, the bundled COLR color emoji font, from
+//! the Noto Emoji outlines and a few plain shapes. Each emoji is a stack of
+//! single-color layers. Rerun after changing the list in `emoji`:
+//!
+//! ```sh
+//! cargo run --example color_emoji
+//! ```
+
+use std::collections::BTreeMap;
+use ttf_parser::{Face, OutlineBuilder, Tag};
+
+const NOTO: &[u8] = include_bytes!("../assets/NotoEmoji-Regular.ttf");
+const OUTPUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/assets/ColorEmoji.ttf");
+
+/// Metrics shared with Noto Emoji, so both fonts line up in a cell.
+const UNITS_PER_EM: u16 = 2048;
+const ADVANCE: u16 = 2600;
+const ASCENDER: i16 = 2189;
+const DESCENDER: i16 = -600;
+
+/// The square most Noto Emoji glyphs fill: center and half its side.
+const CENTER: (f32, f32) = (1300.0, 750.0);
+const HALF: f32 = 950.0;
+
+const RED: u32 = 0xDD2E44;
+const DARK_RED: u32 = 0xBE1931;
+const ORANGE: u32 = 0xF4900C;
+const YELLOW: u32 = 0xFDCB58;
+const GOLD: u32 = 0xFFAC33;
+const GREEN: u32 = 0x78B159;
+const BLUE: u32 = 0x55ACEE;
+const DARK_BLUE: u32 = 0x3B88C3;
+const PURPLE: u32 = 0xAA8ED6;
+const BROWN: u32 = 0xC1694F;
+const BLACK: u32 = 0x31373D;
+const GRAY: u32 = 0x99AAB5;
+const LIGHT_GRAY: u32 = 0xCCD6DD;
+const WHITE: u32 = 0xFFFFFF;
+const SKIN: u32 = 0xFFCC4D;
+const FACE_DARK: u32 = 0x664500;
+
+/// A closed TrueType contour: points in font units, true when on the curve.
+type Contour = Vec<(f32, f32, bool)>;
+
+/// One color layer of an emoji.
+struct Layer {
+    contours: Vec<Contour>,
+    color: u32,
+}
+
+fn layer(contours: Vec<Contour>, color: u32) -> Layer {
+    Layer { contours, color }
+}
+
+/// Collects a glyph's contours. Noto Emoji has quadratic outlines only.
+#[derive(Default)]
+struct Contours(Vec<Contour>);
+
+impl OutlineBuilder for Contours {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.0.push(vec![(x, y, true)]);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        self.0.last_mut().unwrap().push((x, y, true));
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let contour = self.0.last_mut().unwrap();
+        contour.push((x1, y1, false));
+        contour.push((x, y, true));
+    }
+
+    fn curve_to(&mut self, _: f32, _: f32, _: f32, _: f32, _: f32, _: f32) {
+        panic!("cubic curve in a TrueType outline");
+    }
+
+    fn close(&mut self) {
+        let contour = self.0.last_mut().unwrap();
+        if contour.len() > 1
+            && contour.first().map(|p| (p.0, p.1)) == contour.last().map(|p| (p.0, p.1))
+        {
+            contour.pop();
+        }
+    }
+}
+
+/// The Noto Emoji outline of `c`, scaled about the emoji square's center by
+/// `scale` and moved by `offset`.
+fn noto(face: &Face, c: char, scale: f32, offset: (f32, f32)) -> Vec<Contour> {
+    let id = face
+        .glyph_index(c)
+        .unwrap_or_else(|| panic!("Noto Emoji has no {c}"));
+    let mut contours = Contours::default();
+    face.outline_glyph(id, &mut contours);
+    let map = |v: f32, center: f32, shift: f32| center + (v - center) * scale + shift;
+    contours
+        .0
+        .into_iter()
+        .map(|contour| {
+            contour
+                .into_iter()
+                .map(|(x, y, on)| (map(x, CENTER.0, offset.0), map(y, CENTER.1, offset.1), on))
+                .collect()
+        })
+        .collect()
+}
+
+/// Whether `(x, y)` is inside `contour`, taking its points as a polygon.
+fn inside(contour: &Contour, (x, y): (f32, f32)) -> bool {
+    let mut inside = false;
+    for (i, &(x1, y1, _)) in contour.iter().enumerate() {
+        let (x0, y0, _) = contour[(i + contour.len() - 1) % contour.len()];
+        if (y0 > y) != (y1 > y) && x < x0 + (y - y0) * (x1 - x0) / (y1 - y0) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+/// Split an outline into layers by how deeply each contour is nested, and
+/// color depth `d` with `colors[d % colors.len()]`. Holes in a one-color
+/// glyph become areas of the next color.
+fn nested(contours: Vec<Contour>, colors: &[u32]) -> Vec<Layer> {
+    let depths: Vec<usize> = contours
+        .iter()
+        .enumerate()
+        .map(|(i, contour)| {
+            let (x, y, _) = contour[0];
+            contours
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| j != i && inside(other, (x, y)))
+                .count()
+        })
+        .collect();
+    let deepest = depths.iter().copied().max().unwrap_or(0);
+    (0..=deepest)
+        .map(|depth| {
+            let contours = contours
+                .iter()
+                .zip(&depths)
+                .filter(|&(_, &d)| d == depth)
+                .map(|(contour, _)| contour.clone())
+                .collect();
+            layer(contours, colors[depth % colors.len()])
+        })
+        .collect()
+}
+
+/// A polygon through `vertices` (clockwise) with each corner rounded off
+/// over `round` of its two edges.
+fn polygon(vertices: &[(f32, f32)], round: f32) -> Contour {
+    let n = vertices.len();
+    let toward =
+        |a: (f32, f32), b: (f32, f32)| (a.0 + (b.0 - a.0) * round, a.1 + (b.1 - a.1) * round);
+    let mut contour = Vec::new();
+    for i in 0..n {
+        let (previous, vertex, next) = (
+            vertices[(i + n - 1) % n],
+            vertices[i],
+            vertices[(i + 1) % n],
+        );
+        if round > 0.0 {
+            let (a, b) = (toward(vertex, previous), toward(vertex, next));
+            contour.push((a.0, a.1, true));
+            contour.push((vertex.0, vertex.1, false));
+            contour.push((b.0, b.1, true));
+        } else {
+            contour.push((vertex.0, vertex.1, true));
+        }
+    }
+    contour
+}
+
+/// A circle of eight quadratic arcs, clockwise unless `hole`.
+fn circle((cx, cy): (f32, f32), r: f32, hole: bool) -> Contour {
+    let step = std::f32::consts::FRAC_PI_4 * if hole { 1.0 } else { -1.0 };
+    let control = r / (step / 2.0).cos();
+    (0..8)
+        .flat_map(|k| {
+            let (a, m) = (step * k as f32, step * (k as f32 + 0.5));
+            [
+                (cx + r * a.cos(), cy + r * a.sin(), true),
+                (cx + control * m.cos(), cy + control * m.sin(), false),
+            ]
+        })
+        .collect()
+}
+
+/// A rectangle centered on `(cx, cy)` with rounded corners.
+fn rect((cx, cy): (f32, f32), (w, h): (f32, f32), round: f32) -> Contour {
+    let (x0, x1, y0, y1) = (cx - w / 2.0, cx + w / 2.0, cy - h / 2.0, cy + h / 2.0);
+    polygon(&[(x0, y1), (x1, y1), (x1, y0), (x0, y0)], round)
+}
+
+/// The rounded square behind symbols such as ✅ and ⬆️.
+fn tile(color: u32) -> Layer {
+    layer(vec![rect(CENTER, (2.0 * HALF, 2.0 * HALF), 0.12)], color)
+}
+
+fn disc(color: u32) -> Layer {
+    layer(vec![circle(CENTER, HALF, false)], color)
+}
+
+/// A regular polygon of `n` corners around the emoji center, the first
+/// at `start` radians.
+fn regular(n: usize, radius: f32, start: f32, round: f32) -> Contour {
+    let vertices: Vec<_> = (0..n)
+        .map(|k| {
+            let angle = start - std::f32::consts::TAU * k as f32 / n as f32;
+            (
+                CENTER.0 + radius * angle.cos(),
+                CENTER.1 + radius * angle.sin(),
+            )
+        })
+        .collect();
+    polygon(&vertices, round)
+}
+
+fn star(radius: f32) -> Contour {
+    let vertices: Vec<_> = (0..10)
+        .map(|k| {
+            let angle = std::f32::consts::FRAC_PI_2 - std::f32::consts::PI * k as f32 / 5.0;
+            let r = if k % 2 == 0 { radius } else { radius * 0.45 };
+            (
+                CENTER.0 + r * angle.cos(),
+                CENTER.1 - 60.0 + r * angle.sin(),
+            )
+        })
+        .collect();
+    polygon(&vertices, 0.1)
+}
+
+/// The layers of each emoji, bottom first.
+fn emoji(face: &Face) -> Vec<(char, Vec<Layer>)> {
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_8};
+    let heart = |color| vec![layer(noto(face, '❤', 1.0, (0.0, 0.0)), color)];
+    let on_tile = |c, color, symbol: char, scale| {
+        (
+            c,
+            vec![
+                tile(color),
+                layer(noto(face, symbol, scale, (0.0, 0.0)), WHITE),
+            ],
+        )
+    };
+    let bar = |w: f32, h: f32| rect(CENTER, (w, h), 0.3);
+    let mut list = vec![
+        on_tile('✅', GREEN, '✔', 0.75),
+        on_tile('❎', GREEN, '✖', 0.7),
+        on_tile('☑', DARK_BLUE, '✔', 0.75),
+        on_tile('⬆', DARK_BLUE, '⬆', 0.75),
+        on_tile('⬇', DARK_BLUE, '⬇', 0.75),
+        on_tile('⬅', DARK_BLUE, '⬅', 0.75),
+        on_tile('➡', DARK_BLUE, '➡', 0.75),
+        on_tile('▶', DARK_BLUE, '▶', 0.6),
+        ('❌', vec![layer(noto(face, '❌', 1.0, (0.0, 0.0)), RED)]),
+        (
+            '❗',
+            vec![layer(noto(face, '❗', 1.0, (0.0, 0.0)), DARK_RED)],
+        ),
+        (
+            '❕',
+            vec![layer(noto(face, '❗', 1.0, (0.0, 0.0)), LIGHT_GRAY)],
+        ),
+        (
+            '❓',
+            vec![layer(noto(face, '❓', 1.0, (0.0, 0.0)), DARK_RED)],
+        ),
+        (
+            '❔',
+            vec![layer(noto(face, '❓', 1.0, (0.0, 0.0)), LIGHT_GRAY)],
+        ),
+        (
+            '⚠',
+            vec![
+                layer(vec![regular(3, 1150.0, FRAC_PI_2, 0.1)], SKIN),
+                layer(noto(face, '❗', 0.5, (0.0, -120.0)), BLACK),
+            ],
+        ),
+        (
+            '⛔',
+            vec![disc(RED), layer(vec![bar(1300.0, 380.0)], WHITE)],
+        ),
+        (
+            '🚫',
+            vec![layer(
+                vec![
+                    circle(CENTER, HALF, false),
+                    circle(CENTER, HALF * 0.78, true),
+                    polygon(&slash(HALF * 0.9, 170.0), 0.0),
+                ],
+                RED,
+            )],
+        ),
+        (
+            '🛑',
+            vec![
+                layer(vec![regular(8, 1020.0, FRAC_PI_8, 0.05)], RED),
+                layer(
+                    vec![
+                        regular(8, 900.0, FRAC_PI_8, 0.05),
+                        reversed(regular(8, 820.0, FRAC_PI_8, 0.05)),
+                    ],
+                    WHITE,
+                ),
+                layer(vec![bar(1000.0, 280.0)], WHITE),
+            ],
+        ),
+        (
+            '⏳',
+            nested(noto(face, '⏳', 1.0, (0.0, 0.0)), &[GRAY, GOLD]),
+        ),
+        (
+            '⌛',
+            nested(noto(face, '⌛', 1.0, (0.0, 0.0)), &[GRAY, GOLD]),
+        ),
+        (
+            '🚧',
+            nested(noto(face, '🚧', 1.0, (0.0, 0.0)), &[ORANGE, SKIN]),
+        ),
+        (
+            '🚨',
+            nested(noto(face, '🚨', 1.0, (0.0, 0.0)), &[RED, LIGHT_GRAY]),
+        ),
+        (
+            'ℹ',
+            nested(noto(face, 'ℹ', 1.0, (0.0, 0.0)), &[DARK_BLUE, WHITE]),
+        ),
+        (
+            '🆗',
+            nested(noto(face, '🆗', 1.0, (0.0, 0.0)), &[DARK_BLUE, WHITE]),
+        ),
+        (
+            '🆕',
+            nested(noto(face, '🆕', 1.0, (0.0, 0.0)), &[DARK_BLUE, WHITE]),
+        ),
+        (
+            '⏸',
+            vec![
+                tile(DARK_BLUE),
+                layer(
+                    vec![
+                        rect((CENTER.0 - 250.0, CENTER.1), (280.0, 1100.0), 0.2),
+                        rect((CENTER.0 + 250.0, CENTER.1), (280.0, 1100.0), 0.2),
+                    ],
+                    WHITE,
+                ),
+            ],
+        ),
+        (
+            '⏹',
+            vec![
+                tile(DARK_BLUE),
+                layer(vec![rect(CENTER, (1000.0, 1000.0), 0.1)], WHITE),
+            ],
+        ),
+        (
+            '⏺',
+            vec![
+                tile(DARK_BLUE),
+                layer(vec![circle(CENTER, 560.0, false)], WHITE),
+            ],
+        ),
+        ('🔴', vec![disc(RED)]),
+        ('🟠', vec![disc(ORANGE)]),
+        ('🟡', vec![disc(YELLOW)]),
+        ('🟢', vec![disc(GREEN)]),
+        ('🔵', vec![disc(BLUE)]),
+        ('🟣', vec![disc(PURPLE)]),
+        ('🟤', vec![disc(BROWN)]),
+        ('⚫', vec![disc(BLACK)]),
+        ('⚪', vec![disc(LIGHT_GRAY)]),
+        ('🟥', vec![tile(RED)]),
+        ('🟧', vec![tile(ORANGE)]),
+        ('🟨', vec![tile(YELLOW)]),
+        ('🟩', vec![tile(GREEN)]),
+        ('🟦', vec![tile(BLUE)]),
+        ('🟪', vec![tile(PURPLE)]),
+        ('🟫', vec![tile(BROWN)]),
+        ('⬛', vec![tile(BLACK)]),
+        ('⬜', vec![tile(LIGHT_GRAY)]),
+        (
+            '🔶',
+            vec![layer(vec![regular(4, 1050.0, FRAC_PI_2, 0.1)], ORANGE)],
+        ),
+        (
+            '🔷',
+            vec![layer(vec![regular(4, 1050.0, FRAC_PI_2, 0.1)], BLUE)],
+        ),
+        (
+            '🔸',
+            vec![layer(vec![regular(4, 600.0, FRAC_PI_2, 0.1)], ORANGE)],
+        ),
+        (
+            '🔹',
+            vec![layer(vec![regular(4, 600.0, FRAC_PI_2, 0.1)], BLUE)],
+        ),
+        (
+            '🔺',
+            vec![layer(vec![regular(3, 800.0, FRAC_PI_2, 0.1)], RED)],
+        ),
+        (
+            '🔻',
+            vec![layer(vec![regular(3, 800.0, -FRAC_PI_2, 0.1)], RED)],
+        ),
+        ('⭐', vec![layer(vec![star(1080.0)], GOLD)]),
+        (
+            '🌟',
+            nested(noto(face, '🌟', 1.0, (0.0, 0.0)), &[GOLD, SKIN]),
+        ),
+        ('✨', nested(noto(face, '✨', 1.0, (0.0, 0.0)), &[GOLD])),
+        ('❤', heart(RED)),
+        ('🧡', heart(ORANGE)),
+        ('💛', heart(YELLOW)),
+        ('💚', heart(GREEN)),
+        ('💙', heart(BLUE)),
+        ('💜', heart(PURPLE)),
+        ('🖤', heart(BLACK)),
+        ('🤍', heart(LIGHT_GRAY)),
+        ('💔', vec![layer(noto(face, '💔', 1.0, (0.0, 0.0)), RED)]),
+        (
+            '🔥',
+            nested(noto(face, '🔥', 1.0, (0.0, 0.0)), &[ORANGE, SKIN]),
+        ),
+        (
+            '💥',
+            nested(noto(face, '💥', 1.0, (0.0, 0.0)), &[RED, SKIN]),
+        ),
+        (
+            '🎯',
+            nested(noto(face, '🎯', 1.0, (0.0, 0.0)), &[RED, WHITE]),
+        ),
+        (
+            '🔔',
+            nested(noto(face, '🔔', 1.0, (0.0, 0.0)), &[GOLD, BROWN]),
+        ),
+        (
+            '💡',
+            nested(noto(face, '💡', 1.0, (0.0, 0.0)), &[GRAY, SKIN]),
+        ),
+        (
+            '🐛',
+            nested(noto(face, '🐛', 1.0, (0.0, 0.0)), &[GREEN, BLACK]),
+        ),
+        (
+            '🐞',
+            nested(noto(face, '🐞', 1.0, (0.0, 0.0)), &[RED, BLACK]),
+        ),
+        (
+            '🚀',
+            nested(noto(face, '🚀', 1.0, (0.0, 0.0)), &[GRAY, RED]),
+        ),
+        (
+            '🎉',
+            nested(noto(face, '🎉', 1.0, (0.0, 0.0)), &[GOLD, RED]),
+        ),
+        (
+            '🏆',
+            nested(noto(face, '🏆', 1.0, (0.0, 0.0)), &[GOLD, BROWN]),
+        ),
+        (
+            '👍',
+            nested(noto(face, '👍', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '👎',
+            nested(noto(face, '👎', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '🔒',
+            nested(noto(face, '🔒', 1.0, (0.0, 0.0)), &[GOLD, FACE_DARK]),
+        ),
+        (
+            '🔓',
+            nested(noto(face, '🔓', 1.0, (0.0, 0.0)), &[GOLD, FACE_DARK]),
+        ),
+        ('📌', vec![layer(noto(face, '📌', 1.0, (0.0, 0.0)), RED)]),
+        ('📈', vec![layer(noto(face, '📈', 1.0, (0.0, 0.0)), GREEN)]),
+        ('📉', vec![layer(noto(face, '📉', 1.0, (0.0, 0.0)), RED)]),
+        ('💯', vec![layer(noto(face, '💯', 1.0, (0.0, 0.0)), RED)]),
+        (
+            '😀',
+            nested(noto(face, '😀', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '😃',
+            nested(noto(face, '😃', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '😞',
+            nested(noto(face, '😞', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '😢',
+            nested(noto(face, '😢', 1.0, (0.0, 0.0)), &[SKIN, FACE_DARK]),
+        ),
+        (
+            '😡',
+            nested(noto(face, '😡', 1.0, (0.0, 0.0)), &[RED, FACE_DARK]),
+        ),
+    ];
+    list.sort_by_key(|(c, _)| *c);
+    list
+}
+
+/// The diagonal bar of 🚫, from top left to bottom right.
+fn slash(length: f32, width: f32) -> Vec<(f32, f32)> {
+    let diagonal = std::f32::consts::FRAC_1_SQRT_2;
+    let (dx, dy) = (length * diagonal, -length * diagonal);
+    let (nx, ny) = (width / 2.0 * diagonal, width / 2.0 * diagonal);
+    let (cx, cy) = CENTER;
+    vec![
+        (cx - dx - nx, cy - dy - ny),
+        (cx - dx + nx, cy - dy + ny),
+        (cx + dx + nx, cy + dy + ny),
+        (cx + dx - nx, cy + dy - ny),
+    ]
+}
+
+fn reversed(mut contour: Contour) -> Contour {
+    contour.reverse();
+    contour
+}
+
+/// A simple TrueType glyph: header, end points, no instructions, one flag
+/// byte per point and 16-bit coordinate deltas.
+fn glyf_entry(contours: &[Contour]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if contours.is_empty() {
+        return out;
+    }
+    let points: Vec<(i16, i16, bool)> = contours
+        .iter()
+        .flatten()
+        .map(|&(x, y, on)| (x.round() as i16, y.round() as i16, on))
+        .collect();
+    let (x_min, y_min, x_max, y_max) = bounds(&points);
+    put16(&mut out, contours.len() as u16);
+    for v in [x_min, y_min, x_max, y_max] {
+        put16(&mut out, v as u16);
+    }
+    let mut end = 0;
+    for contour in contours {
+        end += contour.len();
+        put16(&mut out, (end - 1) as u16);
+    }
+    put16(&mut out, 0);
+    out.extend(points.iter().map(|p| p.2 as u8));
+    for axis in [0, 1] {
+        let mut last = 0;
+        for p in &points {
+            let v = if axis == 0 { p.0 } else { p.1 };
+            put16(&mut out, v.wrapping_sub(last) as u16);
+            last = v;
+        }
+    }
+    while out.len() % 4 != 0 {
+        out.push(0);
+    }
+    out
+}
+
+fn bounds(points: &[(i16, i16, bool)]) -> (i16, i16, i16, i16) {
+    points.iter().fold(
+        (i16::MAX, i16::MAX, i16::MIN, i16::MIN),
+        |(x0, y0, x1, y1), &(x, y, _)| (x0.min(x), y0.min(y), x1.max(x), y1.max(y)),
+    )
+}
+
+fn put16(out: &mut Vec<u8>, v: u16) {
+    out.extend(v.to_be_bytes());
+}
+
+fn put32(out: &mut Vec<u8>, v: u32) {
+    out.extend(v.to_be_bytes());
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+/// The `name` table: Windows Unicode records in UTF-16BE.
+fn name_table(names: &[(u16, &str)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    let mut strings = Vec::new();
+    put16(&mut out, 0);
+    put16(&mut out, names.len() as u16);
+    put16(&mut out, (6 + 12 * names.len()) as u16);
+    for &(id, text) in names {
+        let encoded: Vec<u8> = text.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        for v in [3, 1, 0x409, id, encoded.len() as u16, strings.len() as u16] {
+            put16(&mut out, v);
+        }
+        strings.extend(encoded);
+    }
+    out.extend(strings);
+    out
+}
+
+/// Assemble the font file from its tables, with checksums and the `head`
+/// checksum adjustment.
+fn sfnt(mut tables: BTreeMap<[u8; 4], Vec<u8>>) -> Vec<u8> {
+    let count = tables.len() as u16;
+    let power = 1 << (15 - count.leading_zeros());
+    let mut out = Vec::new();
+    put32(&mut out, 0x00010000);
+    for v in [
+        count,
+        power * 16,
+        power.trailing_zeros() as u16,
+        count * 16 - power * 16,
+    ] {
+        put16(&mut out, v);
+    }
+    let mut offset = 12 + 16 * tables.len();
+    let mut body = Vec::new();
+    for (tag, data) in &mut tables {
+        out.extend(tag);
+        put32(&mut out, checksum(data));
+        put32(&mut out, offset as u32);
+        put32(&mut out, data.len() as u32);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset += data.len();
+        body.extend_from_slice(data);
+    }
+    out.extend(body);
+    let adjustment = 0xB1B0AFBAu32.wrapping_sub(checksum(&out));
+    let position = tables.keys().position(|t| t == b"head").unwrap();
+    let record = 12 + 16 * position;
+    let table = u32::from_be_bytes(out[record + 8..record + 12].try_into().unwrap()) as usize;
+    out[table + 8..table + 12].copy_from_slice(&adjustment.to_be_bytes());
+    out
+}
+
+fn main() {
+    let face = Face::parse(NOTO, 0).expect("Failed to parse Noto Emoji");
+    let list = emoji(&face);
+
+    // Glyph 0 is .notdef, then each emoji's base glyph (the Noto outline
+    // when there is one, for renderers without COLR), then its layers
+    let mut glyphs: Vec<Vec<Contour>> = vec![Vec::new()];
+    let mut palette: Vec<u32> = Vec::new();
+    let mut bases = Vec::new();
+    let mut layers = Vec::new();
+    let mut cmap = Vec::new();
+    for (c, emoji_layers) in list {
+        let base = glyphs.len() as u16;
+        let fallback = face
+            .glyph_index(c)
+            .map(|_| noto(&face, c, 1.0, (0.0, 0.0)))
+            .unwrap_or_else(|| emoji_layers[0].contours.clone());
+        glyphs.push(fallback);
+        cmap.push((c as u32, base));
+        bases.push((base, layers.len() as u16, emoji_layers.len() as u16));
+        for Layer { contours, color } in emoji_layers {
+            let index = palette.iter().position(|&p| p == color).unwrap_or_else(|| {
+                palette.push(color);
+                palette.len() - 1
+            });
+            layers.push((glyphs.len() as u16, index as u16));
+            glyphs.push(contours);
+        }
+    }
+    let count = glyphs.len() as u16;
+
+    let mut glyf = Vec::new();
+    let mut loca = Vec::new();
+    let mut hmtx = Vec::new();
+    let mut extent = (i16::MAX, i16::MAX, i16::MIN, i16::MIN);
+    let (mut max_points, mut max_contours) = (0, 0);
+    for contours in &glyphs {
+        put32(&mut loca, glyf.len() as u32);
+        let entry = glyf_entry(contours);
+        let points: Vec<_> = contours
+            .iter()
+            .flatten()
+            .map(|&(x, y, on)| (x.round() as i16, y.round() as i16, on))
+            .collect();
+        let left = if points.is_empty() {
+            0
+        } else {
+            let b = bounds(&points);
+            extent = (
+                extent.0.min(b.0),
+                extent.1.min(b.1),
+                extent.2.max(b.2),
+                extent.3.max(b.3),
+            );
+            b.0
+        };
+        max_points = max_points.max(points.len());
+        max_contours = max_contours.max(contours.len());
+        put16(&mut hmtx, ADVANCE);
+        put16(&mut hmtx, left as u16);
+        glyf.extend(entry);
+    }
+    put32(&mut loca, glyf.len() as u32);
+
+    let mut head = Vec::new();
+    put32(&mut head, 0x00010000);
+    put32(&mut head, 0x00010000);
+    put32(&mut head, 0);
+    put32(&mut head, 0x5F0F3CF5);
+    put16(&mut head, 0x000B);
+    put16(&mut head, UNITS_PER_EM);
+    head.extend([0; 16]);
+    for v in [extent.0, extent.1, extent.2, extent.3] {
+        put16(&mut head, v as u16);
+    }
+    for v in [0, 8, 2, 1, 0] {
+        put16(&mut head, v);
+    }
+
+    let mut hhea = Vec::new();
+    put32(&mut hhea, 0x00010000);
+    for v in [ASCENDER, DESCENDER, 0] {
+        put16(&mut hhea, v as u16);
+    }
+    put16(&mut hhea, ADVANCE);
+    for v in [
+        extent.0,
+        ADVANCE as i16 - extent.2,
+        extent.2,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ] {
+        put16(&mut hhea, v as u16);
+    }
+    put16(&mut hhea, count);
+
+    let mut maxp = Vec::new();
+    put32(&mut maxp, 0x00010000);
+    for v in [
+        count,
+        max_points as u16,
+        max_contours as u16,
+        0,
+        0,
+        2,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    ] {
+        put16(&mut maxp, v);
+    }
+
+    let mut cmap_table = Vec::new();
+    for v in [0, 2, 0, 4] {
+        put16(&mut cmap_table, v);
+    }
+    put32(&mut cmap_table, 20);
+    put16(&mut cmap_table, 3);
+    put16(&mut cmap_table, 10);
+    put32(&mut cmap_table, 20);
+    put16(&mut cmap_table, 12);
+    put16(&mut cmap_table, 0);
+    put32(&mut cmap_table, 16 + 12 * cmap.len() as u32);
+    put32(&mut cmap_table, 0);
+    put32(&mut cmap_table, cmap.len() as u32);
+    for &(c, id) in &cmap {
+        put32(&mut cmap_table, c);
+        put32(&mut cmap_table, c);
+        put32(&mut cmap_table, id as u32);
+    }
+
+    let mut colr = Vec::new();
+    put16(&mut colr, 0);
+    put16(&mut colr, bases.len() as u16);
+    put32(&mut colr, 14);
+    put32(&mut colr, 14 + 6 * bases.len() as u32);
+    put16(&mut colr, layers.len() as u16);
+    for &(base, first, n) in &bases {
+        for v in [base, first, n] {
+            put16(&mut colr, v);
+        }
+    }
+    for &(id, index) in &layers {
+        put16(&mut colr, id);
+        put16(&mut colr, index);
+    }
+
+    let mut cpal = Vec::new();
+    for v in [0, palette.len() as u16, 1, palette.len() as u16] {
+        put16(&mut cpal, v);
+    }
+    put32(&mut cpal, 14);
+    put16(&mut cpal, 0);
+    for &rgb in &palette {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        cpal.extend([b, g, r, 255]);
+    }
+
+    let mut post = Vec::new();
+    put32(&mut post, 0x00030000);
+    put32(&mut post, 0);
+    put16(&mut post, (-100i16) as u16);
+    put16(&mut post, 50);
+    post.extend([0; 20]);
+
+    // Noto Emoji's OS/2 with this font's first and last characters
+    let mut os2 = face
+        .raw_face()
+        .table(Tag::from_bytes(b"OS/2"))
+        .expect("Noto Emoji has an OS/2 table")
+        .to_vec();
+    let first = cmap.first().map_or(0, |&(c, _)| c.min(0xFFFF) as u16);
+    let last = cmap.last().map_or(0, |&(c, _)| c.min(0xFFFF) as u16);
+    os2[64..66].copy_from_slice(&first.to_be_bytes());
+    os2[66..68].copy_from_slice(&last.to_be_bytes());
+
+    let name = name_table(&[
+        (0, "Copyright 2013 Google Inc. All Rights Reserved."),
+        (1, "table2img Color Emoji"),
+        (2, "Regular"),
+        (4, "table2img Color Emoji"),
+        (5, "Version 1.000"),
+        (6, "table2imgColorEmoji-Regular"),
+        (
+            10,
+            "Color layers over Noto Emoji outlines for the status emoji of tables.",
+        ),
+        (
+            13,
+            "This Font Software is licensed under the SIL Open Font License, Version 1.1.",
+        ),
+        (14, "https://openfontlicense.org"),
+    ]);
+
+    let tables = BTreeMap::from([
+        (*b"COLR", colr),
+        (*b"CPAL", cpal),
+        (*b"OS/2", os2),
+        (*b"cmap", cmap_table),
+        (*b"glyf", glyf),
+        (*b"head", head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", loca),
+        (*b"maxp", maxp),
+        (*b"name", name),
+        (*b"post", post),
+    ]);
+    std::fs::write(OUTPUT, sfnt(tables)).expect("Failed to write the font");
+    println!("{OUTPUT}: {} emoji, {count} glyphs", cmap.len());
+}
@@ -0,0 +1,507 @@
+use ab_glyph::{point, Font, FontRef, GlyphId, Point, PxScale, ScaleFont};
+use ab_glyph_rasterizer::Rasterizer;
+use image::{Rgb, RgbImage};
+use ttf_parser::colr::{ClipBox, ColorStop, CompositeMode, GradientExtend, Paint, Painter};
+use ttf_parser::{Face, OutlineBuilder, RgbaColor, Transform};
+
+/// One piece of an outline, in image pixels.
+#[derive(Clone, Copy)]
+enum Segment {
+    Line(Point, Point),
+    Quad(Point, Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+/// How a region is filled: a color, or a gradient with its stops sorted by
+/// offset and the transform from font units to gradient space.
+enum Fill {
+    Solid(RgbaColor),
+    Gradient {
+        shape: Shape,
+        stops: Vec<(f32, RgbaColor)>,
+        extend: GradientExtend,
+        inverse: Transform,
+    },
+}
+
+enum Shape {
+    /// Start point and the end point projected as the COLR spec describes
+    Linear(Point, Point),
+    /// Start circle and end circle as (center, radius)
+    Radial((Point, f32), (Point, f32)),
+    /// Center and start and end angles in degrees, counterclockwise
+    Sweep(Point, f32, f32),
+}
+
+/// Collects the fills a COLR glyph paints, in order. Composite modes other
+/// than source-over are drawn as source-over, and a paint is clipped by the
+/// innermost clip only.
+struct Canvas<'f> {
+    face: &'f Face<'f>,
+    palette: u16,
+    /// Font units to pixels: origin on the baseline and pixels per unit
+    origin: Point,
+    units: f32,
+    transforms: Vec<Transform>,
+    outline: Vec<Segment>,
+    clips: Vec<Vec<Segment>>,
+    fills: Vec<(Vec<Segment>, Fill)>,
+}
+
+/// Feeds a glyph outline into `Segment`s through a transform.
+struct PathBuilder<'c> {
+    transform: Transform,
+    origin: Point,
+    units: f32,
+    segments: &'c mut Vec<Segment>,
+    start: Point,
+    last: Point,
+}
+
+impl PathBuilder<'_> {
+    fn map(&self, x: f32, y: f32) -> Point {
+        let t = self.transform;
+        let (x, y) = (t.a * x + t.c * y + t.e, t.b * x + t.d * y + t.f);
+        point(
+            self.origin.x + x * self.units,
+            self.origin.y - y * self.units,
+        )
+    }
+}
+
+impl OutlineBuilder for PathBuilder<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        self.start = self.map(x, y);
+        self.last = self.start;
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let p = self.map(x, y);
+        self.segments.push(Segment::Line(self.last, p));
+        self.last = p;
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (c, p) = (self.map(x1, y1), self.map(x, y));
+        self.segments.push(Segment::Quad(self.last, c, p));
+        self.last = p;
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (c1, c2, p) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.segments.push(Segment::Cubic(self.last, c1, c2, p));
+        self.last = p;
+    }
+
+    fn close(&mut self) {
+        if self.last != self.start {
+            self.segments.push(Segment::Line(self.last, self.start));
+        }
+        self.last = self.start;
+    }
+}
+
+impl Canvas<'_> {
+    fn transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+
+    fn builder<'c>(&self, segments: &'c mut Vec<Segment>) -> PathBuilder<'c> {
+        PathBuilder {
+            transform: self.transform(),
+            origin: self.origin,
+            units: self.units,
+            segments,
+            start: point(0.0, 0.0),
+            last: point(0.0, 0.0),
+        }
+    }
+}
+
+impl<'a> Painter<'a> for Canvas<'_> {
+    fn outline_glyph(&mut self, glyph_id: ttf_parser::GlyphId) {
+        let mut segments = Vec::new();
+        self.face
+            .outline_glyph(glyph_id, &mut self.builder(&mut segments));
+        self.outline = segments;
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        let coords = self.face.variation_coordinates();
+        let palette = self.palette;
+        let gradient = |shape, stops, extend, canvas: &Self| Fill::Gradient {
+            shape,
+            stops,
+            extend,
+            inverse: invert(canvas.transform()),
+        };
+        let fill = match paint {
+            Paint::Solid(color) => Fill::Solid(color),
+            Paint::LinearGradient(g) => {
+                let (p0, p1, p2) = (point(g.x0, g.y0), point(g.x1, g.y1), point(g.x2, g.y2));
+                // Project p1 onto the line through p0 perpendicular to p0-p2
+                let normal = point(p2.y - p0.y, -(p2.x - p0.x));
+                let length = dot(normal, normal);
+                let end = if length > 0.0 {
+                    let k = dot(p1 - p0, normal) / length;
+                    point(p0.x + normal.x * k, p0.y + normal.y * k)
+                } else {
+                    p1
+                };
+                let stops = sorted_stops(g.stops(palette, coords));
+                gradient(Shape::Linear(p0, end), stops, g.extend, self)
+            }
+            Paint::RadialGradient(g) => {
+                let shape = Shape::Radial((point(g.x0, g.y0), g.r0), (point(g.x1, g.y1), g.r1));
+                let stops = sorted_stops(g.stops(palette, coords));
+                gradient(shape, stops, g.extend, self)
+            }
+            Paint::SweepGradient(g) => {
+                let shape = Shape::Sweep(
+                    point(g.center_x, g.center_y),
+                    g.start_angle * 180.0,
+                    g.end_angle * 180.0,
+                );
+                let stops = sorted_stops(g.stops(palette, coords));
+                gradient(shape, stops, g.extend, self)
+            }
+        };
+        let region = self.clips.last().unwrap_or(&self.outline).clone();
+        self.fills.push((region, fill));
+    }
+
+    fn push_clip(&mut self) {
+        self.clips.push(self.outline.clone());
+    }
+
+    fn push_clip_box(&mut self, clip: ClipBox) {
+        let mut segments = Vec::new();
+        let mut builder = self.builder(&mut segments);
+        builder.move_to(clip.x_min, clip.y_min);
+        builder.line_to(clip.x_max, clip.y_min);
+        builder.line_to(clip.x_max, clip.y_max);
+        builder.line_to(clip.x_min, clip.y_max);
+        builder.close();
+        self.clips.push(segments);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn push_layer(&mut self, _: CompositeMode) {}
+
+    fn pop_layer(&mut self) {}
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.transforms
+            .push(Transform::combine(self.transform(), transform));
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
+
+/// Stops of a color line sorted by offset.
+fn sorted_stops(stops: impl Iterator<Item = ColorStop>) -> Vec<(f32, RgbaColor)> {
+    let mut stops: Vec<_> = stops.map(|s| (s.stop_offset, s.color)).collect();
+    stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+    stops
+}
+
+fn dot(a: Point, b: Point) -> f32 {
+    a.x * b.x + a.y * b.y
+}
+
+fn invert(t: Transform) -> Transform {
+    let det = t.a * t.d - t.b * t.c;
+    if det == 0.0 {
+        return Transform::default();
+    }
+    let (a, b, c, d) = (t.d / det, -t.b / det, -t.c / det, t.a / det);
+    Transform::new(a, b, c, d, -(a * t.e + c * t.f), -(b * t.e + d * t.f))
+}
+
+impl Fill {
+    /// Color at `p` in font units, or `None` outside a radial gradient's
+    /// cone.
+    fn color_at(&self, p: Point) -> Option<RgbaColor> {
+        let (shape, stops, extend, inverse) = match self {
+            Fill::Solid(color) => return Some(*color),
+            Fill::Gradient {
+                shape,
+                stops,
+                extend,
+                inverse,
+            } => (shape, stops, *extend, inverse),
+        };
+        let t = inverse;
+        let p = point(t.a * p.x + t.c * p.y + t.e, t.b * p.x + t.d * p.y + t.f);
+        let offset = match *shape {
+            Shape::Linear(p0, p1) => {
+                let axis = p1 - p0;
+                let length = dot(axis, axis);
+                if length == 0.0 {
+                    0.0
+                } else {
+                    dot(p - p0, axis) / length
+                }
+            }
+            Shape::Radial((c0, r0), (c1, r1)) => radial_offset(p, (c0, r0), (c1, r1))?,
+            Shape::Sweep(center, start, end) => {
+                let angle = (p.y - center.y).atan2(p.x - center.x).to_degrees();
+                let angle = angle.rem_euclid(360.0);
+                if end == start {
+                    0.0
+                } else {
+                    (angle - start) / (end - start)
+                }
+            }
+        };
+        Some(stop_color(stops, extend_offset(offset, extend)))
+    }
+}
+
+/// Largest `t` for which `p` lies on the circle interpolated between the
+/// start and end circles with a non-negative radius.
+fn radial_offset(p: Point, (c0, r0): (Point, f32), (c1, r1): (Point, f32)) -> Option<f32> {
+    let (dc, dr, dp) = (c1 - c0, r1 - r0, p - c0);
+    let a = dot(dc, dc) - dr * dr;
+    let b = dot(dp, dc) + r0 * dr;
+    let c = dot(dp, dp) - r0 * r0;
+    if a.abs() < f32::EPSILON {
+        return (b != 0.0)
+            .then(|| c / (2.0 * b))
+            .filter(|t| r0 + t * dr >= 0.0);
+    }
+    let discriminant = b * b - a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    [(b + root) / a, (b - root) / a]
+        .into_iter()
+        .filter(|t| r0 + t * dr >= 0.0)
+        .reduce(f32::max)
+}
+
+fn extend_offset(t: f32, extend: GradientExtend) -> f32 {
+    match extend {
+        GradientExtend::Pad => t.clamp(0.0, 1.0),
+        GradientExtend::Repeat => t.rem_euclid(1.0),
+        GradientExtend::Reflect => {
+            let t = t.rem_euclid(2.0);
+            if t > 1.0 {
+                2.0 - t
+            } else {
+                t
+            }
+        }
+    }
+}
+
+fn stop_color(stops: &[(f32, RgbaColor)], t: f32) -> RgbaColor {
+    let Some(&(first_offset, first)) = stops.first() else {
+        return RgbaColor::new(0, 0, 0, 0);
+    };
+    if t <= first_offset {
+        return first;
+    }
+    for pair in stops.windows(2) {
+        let ((o0, c0), (o1, c1)) = (pair[0], pair[1]);
+        if t <= o1 {
+            let k = if o1 > o0 { (t - o0) / (o1 - o0) } else { 1.0 };
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * k).round() as u8;
+            return RgbaColor::new(
+                mix(c0.red, c1.red),
+                mix(c0.green, c1.green),
+                mix(c0.blue, c1.blue),
+                mix(c0.alpha, c1.alpha),
+            );
+        }
+    }
+    stops[stops.len() - 1].1
+}
+
+/// Draw the COLR color glyph `font` has for `id` with its origin at `x` on
+/// `baseline`, in the font's first palette; layers in the text color use
+/// `color`. Returns false when the glyph has no color version.
+pub fn draw(
+    img: &mut RgbImage,
+    font: &FontRef,
+    id: GlyphId,
+    scale: PxScale,
+    (x, baseline): (f32, f32),
+    color: Rgb<u8>,
+) -> bool {
+    let Ok(face) = Face::parse(font.font_data(), 0) else {
+        return false;
+    };
+    let glyph = ttf_parser::GlyphId(id.0);
+    if !face.is_color_glyph(glyph) {
+        return false;
+    }
+    let mut canvas = Canvas {
+        face: &face,
+        palette: 0,
+        origin: point(x, baseline),
+        units: font.as_scaled(scale).scale_factor().vertical,
+        transforms: Vec::new(),
+        outline: Vec::new(),
+        clips: Vec::new(),
+        fills: Vec::new(),
+    };
+    let foreground = RgbaColor::new(color[0], color[1], color[2], 255);
+    if face
+        .paint_color_glyph(glyph, 0, foreground, &mut canvas)
+        .is_none()
+    {
+        return false;
+    }
+    for (region, fill) in &canvas.fills {
+        fill_region(img, region, fill, canvas.origin, canvas.units);
+    }
+    true
+}
+
+/// Rasterize `region` and blend `fill` over `img` where it covers.
+fn fill_region(img: &mut RgbImage, region: &[Segment], fill: &Fill, origin: Point, units: f32) {
+    let points = region.iter().flat_map(|segment| match *segment {
+        Segment::Line(a, b) => vec![a, b],
+        Segment::Quad(a, b, c) => vec![a, b, c],
+        Segment::Cubic(a, b, c, d) => vec![a, b, c, d],
+    });
+    let (mut min, mut max) = (point(f32::MAX, f32::MAX), point(f32::MIN, f32::MIN));
+    for p in points {
+        (min.x, min.y) = (min.x.min(p.x), min.y.min(p.y));
+        (max.x, max.y) = (max.x.max(p.x), max.y.max(p.y));
+    }
+    let left = min.x.floor().max(0.0);
+    let top = min.y.floor().max(0.0);
+    let right = max.x.ceil().min(img.width() as f32);
+    let bottom = max.y.ceil().min(img.height() as f32);
+    if right <= left || bottom <= top {
+        return;
+    }
+
+    let offset = |p: Point| point(p.x - left, p.y - top);
+    let mut rasterizer = Rasterizer::new((right - left) as usize, (bottom - top) as usize);
+    for segment in region {
+        match *segment {
+            Segment::Line(a, b) => rasterizer.draw_line(offset(a), offset(b)),
+            Segment::Quad(a, b, c) => rasterizer.draw_quad(offset(a), offset(b), offset(c)),
+            Segment::Cubic(a, b, c, d) => {
+                rasterizer.draw_cubic(offset(a), offset(b), offset(c), offset(d))
+            }
+        }
+    }
+    rasterizer.for_each_pixel_2d(|gx, gy, coverage| {
+        if coverage <= 0.0 {
+            return;
+        }
+        let (px, py) = (left as u32 + gx, top as u32 + gy);
+        // Pixel center in font units, y up
+        let at = point(
+            (px as f32 + 0.5 - origin.x) / units,
+            (origin.y - py as f32 - 0.5) / units,
+        );
+        let Some(color) = fill.color_at(at) else {
+            return;
+        };
+        let alpha = coverage.min(1.0) * color.alpha as f32 / 255.0;
+        let under = img.get_pixel_mut(px, py);
+        let mix = |f: u8, b: u8| (b as f32 + (f as f32 - b as f32) * alpha).round() as u8;
+        *under = Rgb([
+            mix(color.red, under[0]),
+            mix(color.green, under[1]),
+            mix(color.blue, under[2]),
+        ]);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: RgbaColor = RgbaColor {
+        red: 255,
+        green: 0,
+        blue: 0,
+        alpha: 255,
+    };
+    const BLUE: RgbaColor = RgbaColor {
+        red: 0,
+        green: 0,
+        blue: 255,
+        alpha: 255,
+    };
+
+    #[test]
+    fn stop_interpolation() {
+        let stops = [(0.25, RED), (0.75, BLUE)];
+        assert_eq!(stop_color(&stops, 0.0), RED);
+        assert_eq!(stop_color(&stops, 0.5), RgbaColor::new(128, 0, 128, 255));
+        assert_eq!(stop_color(&stops, 1.0), BLUE);
+        assert_eq!(stop_color(&[], 0.5).alpha, 0);
+    }
+
+    #[test]
+    fn extend_modes() {
+        assert_eq!(extend_offset(1.25, GradientExtend::Pad), 1.0);
+        assert_eq!(extend_offset(-0.5, GradientExtend::Pad), 0.0);
+        assert_eq!(extend_offset(1.25, GradientExtend::Repeat), 0.25);
+        assert_eq!(extend_offset(1.25, GradientExtend::Reflect), 0.75);
+        assert_eq!(extend_offset(-0.25, GradientExtend::Reflect), 0.25);
+    }
+
+    #[test]
+    fn radial_offsets() {
+        // Concentric circles of radius 0 and 100: t is the distance / 100
+        let (start, end) = ((point(0.0, 0.0), 0.0), (point(0.0, 0.0), 100.0));
+        let t = radial_offset(point(30.0, 40.0), start, end).unwrap();
+        assert!((t - 0.5).abs() < 1e-6);
+        // Outside the cone between two circles of equal radius nothing paints
+        let (start, end) = ((point(0.0, 0.0), 10.0), (point(100.0, 0.0), 10.0));
+        assert!(radial_offset(point(50.0, 50.0), start, end).is_none());
+        let t = radial_offset(point(50.0, 0.0), start, end).unwrap();
+        assert!((t - 0.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn linear_gradients_follow_the_transform() {
+        let fill = Fill::Gradient {
+            shape: Shape::Linear(point(0.0, 0.0), point(100.0, 0.0)),
+            stops: vec![(0.0, RED), (1.0, BLUE)],
+            extend: GradientExtend::Pad,
+            // Gradient space scaled up twice and moved 50 units right
+            inverse: invert(Transform::new(2.0, 0.0, 0.0, 2.0, 50.0, 0.0)),
+        };
+        assert_eq!(fill.color_at(point(50.0, 10.0)), Some(RED));
+        assert_eq!(
+            fill.color_at(point(150.0, -30.0)),
+            Some(RgbaColor::new(128, 0, 128, 255))
+        );
+        assert_eq!(fill.color_at(point(250.0, 0.0)), Some(BLUE));
+    }
+
+    #[test]
+    fn bundled_emoji_are_layered() {
+        let font = FontRef::try_from_slice(include_bytes!("../assets/ColorEmoji.ttf")).unwrap();
+        let (scale, origin, text) = (PxScale::from(60.0), (0.0, 60.0), Rgb([9, 9, 9]));
+        let mut img = RgbImage::new(80, 80);
+        assert!(draw(
+            &mut img,
+            &font,
+            font.glyph_id('✅'),
+            scale,
+            origin,
+            text
+        ));
+        // A green tile behind a white check
+        assert!(img.pixels().any(|p| *p == Rgb([0x78, 0xB1, 0x59])));
+        assert!(img.pixels().any(|p| *p == Rgb([255, 255, 255])));
+        // Glyphs without layers are left to the outline fallback
+        assert!(!draw(&mut img, &font, GlyphId(0), scale, origin, text));
+    }
+}
@@ -0,0 +1,85 @@
+use crate::table::Table;
+
+/// Read CSV or TSV `input` split on `delimiter`, with RFC 4180 quoting:
+/// fields may be wrapped in double quotes, contain the delimiter and line
+/// breaks, and escape a quote by doubling it. Without `has_header`, columns
+/// are unnamed and every record is a row. Records with a different number
+/// of fields than the first are padded or truncated, with a warning.
+pub fn parse_delimited(input: &str, delimiter: u8, has_header: bool) -> Option<Table> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+    let mut records = reader.records().map(|record| {
+        record
+            .expect("Failed to read CSV record")
+            .iter()
+            .map(|field| field.replace("\r\n", "\n"))
+            .collect::<Vec<String>>()
+    });
+
+    let first = records.next()?;
+    let num_cols = first.len();
+    let (headers, mut rows) = if has_header {
+        (first, Vec::new())
+    } else {
+        (vec![String::new(); num_cols], vec![first])
+    };
+    let mut warnings = Vec::new();
+    for mut row in records {
+        if row.len() != num_cols {
+            warnings.push(format!(
+                "record {} has {} fields but the first has {}; {}",
+                rows.len() + 1,
+                row.len(),
+                num_cols,
+                if row.len() < num_cols {
+                    "padding with empty cells"
+                } else {
+                    "ignoring the extra fields"
+                }
+            ));
+            row.resize(num_cols, String::new());
+        }
+        rows.push(row);
+    }
+
+    Some(Table {
+        headers,
+        aligns: vec![None; num_cols],
+        rows,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(row: &[&str]) -> Vec<String> {
+        row.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn quoted_fields() {
+        let input = "name,notes\r\n\"Smith, J\",\"said \"\"hi\"\"\r\ntwice\"\r\n";
+        let table = parse_delimited(input, b',', true).unwrap();
+        assert_eq!(table.headers, cells(&["name", "notes"]));
+        assert_eq!(table.rows, vec![cells(&["Smith, J", "said \"hi\"\ntwice"])]);
+    }
+
+    #[test]
+    fn tabs_without_header() {
+        let table = parse_delimited("a\tb\nc\td\n", b'\t', false).unwrap();
+        assert_eq!(table.headers, cells(&["", ""]));
+        assert_eq!(table.rows, vec![cells(&["a", "b"]), cells(&["c", "d"])]);
+    }
+
+    #[test]
+    fn ragged_records() {
+        let table = parse_delimited("a;b\n1\n1;2;3\n", b';', true).unwrap();
+        assert_eq!(table.rows, vec![cells(&["1", ""]), cells(&["1", "2"])]);
+        assert_eq!(table.warnings.len(), 2);
+    }
+}
@@ -0,0 +1,275 @@
+use crate::colr;
+use crate::inline::SpanStyle;
+use ab_glyph::{point, Font, FontRef, GlyphId, GlyphImageFormat, PxScale, ScaleFont};
+use image::imageops::{self, FilterType};
+use image::{Rgb, RgbImage};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// DejaVu Sans, for arrows, dingbats, math, box drawing and other symbols
+/// the cell fonts lack.
+const SYMBOLS_DATA: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+/// COLR color versions of the emoji tables use most, built from Noto Emoji
+/// by `examples/color_emoji.rs`.
+const COLOR_EMOJI_DATA: &[u8] = include_bytes!("../assets/ColorEmoji.ttf");
+
+/// Noto Emoji, outlines for the rest of the emoji, drawn in the text color.
+const EMOJI_DATA: &[u8] = include_bytes!("../assets/NotoEmoji-Regular.ttf");
+
+/// Horizontal slant of synthesized italics, as x offset per pixel of height.
+const ITALIC_SLANT: f32 = 0.2;
+
+/// The fonts a grapheme can be drawn from.
+pub struct Fonts<'a> {
+    /// Cell text
+    pub text: FontRef<'a>,
+    /// Code spans, and the width of a cell
+    pub code: FontRef<'a>,
+    pub scale: PxScale,
+    symbols: FontRef<'a>,
+    /// Tried first for wide graphemes: `--emoji-font` if given, then the
+    /// bundled color and outline emoji
+    emoji: Vec<FontRef<'a>>,
+}
+
+/// A glyph placed `x` pixels from the start of its run.
+struct Placed<'f> {
+    font: &'f FontRef<'f>,
+    id: GlyphId,
+    x: f32,
+    emoji: bool,
+}
+
+/// Characters of an emoji sequence that are not drawn on their own:
+/// variation selectors, the zero-width joiner, skin tone modifiers and tags.
+fn is_emoji_modifier(c: char) -> bool {
+    matches!(
+        c,
+        '\u{FE0E}' | '\u{FE0F}' | '\u{200D}' | '\u{1F3FB}'..='\u{1F3FF}' | '\u{E0020}'..='\u{E007F}'
+    )
+}
+
+fn covers(font: &FontRef, c: char) -> bool {
+    font.glyph_id(c).0 != 0
+}
+
+/// Display width of `grapheme` in cells: 2 for emoji and other wide
+/// characters, 1 for most others.
+pub fn cells(grapheme: &str) -> usize {
+    grapheme.width()
+}
+
+impl<'a> Fonts<'a> {
+    /// `text` and `code` at `scale` with the bundled fallbacks, and
+    /// `color_emoji` ahead of them for emoji.
+    pub fn new(
+        text: FontRef<'a>,
+        code: FontRef<'a>,
+        color_emoji: Option<FontRef<'a>>,
+        scale: PxScale,
+    ) -> Self {
+        let bundled = |data: &'static [u8]| {
+            FontRef::try_from_slice(data).expect("Failed to load fallback font")
+        };
+        Fonts {
+            text,
+            code,
+            scale,
+            symbols: bundled(SYMBOLS_DATA),
+            emoji: color_emoji
+                .into_iter()
+                .chain([bundled(COLOR_EMOJI_DATA), bundled(EMOJI_DATA)])
+                .collect(),
+        }
+    }
+
+    /// Width of one cell, the advance of the monospace code font.
+    pub fn cell_width(&self) -> f32 {
+        self.code
+            .as_scaled(self.scale)
+            .h_advance(self.code.glyph_id('M'))
+    }
+
+    /// Place each grapheme of `text` in the first font that covers it: the
+    /// cell (or code) font, then symbols, then emoji, except that wide
+    /// graphemes try the emoji fonts first. Emoji take two cells, centered;
+    /// sequences such as ZWJ families draw only their first emoji. Returns
+    /// the glyphs and the run's width.
+    fn layout(&self, text: &str, code: bool) -> (Vec<Placed<'_>>, f32) {
+        let scale = self.scale;
+        let primary = if code { &self.code } else { &self.text };
+        let mut placed = Vec::new();
+        let mut caret = 0.0;
+        let mut previous: Option<(&FontRef, GlyphId)> = None;
+        for grapheme in text.graphemes(true) {
+            let chars: Vec<char> = grapheme
+                .chars()
+                .filter(|&c| !is_emoji_modifier(c))
+                .collect();
+            let Some(&first) = chars.first() else {
+                continue;
+            };
+
+            let emoji_font = (cells(grapheme) >= 2)
+                .then(|| self.emoji.iter().find(|f| covers(f, first)))
+                .flatten();
+            if let Some(font) = emoji_font {
+                let id = font.glyph_id(first);
+                let advance = font.as_scaled(scale).h_advance(id);
+                let box_width = 2.0 * self.cell_width();
+                placed.push(Placed {
+                    font,
+                    id,
+                    x: caret + (box_width - advance) / 2.0,
+                    emoji: true,
+                });
+                caret += box_width;
+                previous = None;
+                continue;
+            }
+
+            let font = std::iter::once(primary)
+                .chain([&self.symbols])
+                .chain(&self.emoji)
+                .find(|f| chars.iter().all(|&c| covers(f, c)))
+                .unwrap_or(primary);
+            let scaled = font.as_scaled(scale);
+            for c in chars {
+                let id = font.glyph_id(c);
+                if let Some((previous_font, previous_id)) = previous {
+                    if std::ptr::eq(previous_font, font) {
+                        caret += scaled.kern(previous_id, id);
+                    }
+                }
+                placed.push(Placed {
+                    font,
+                    id,
+                    x: caret,
+                    emoji: false,
+                });
+                caret += scaled.h_advance(id);
+                previous = Some((font, id));
+            }
+        }
+        (placed, caret)
+    }
+
+    /// Width of `text` in `style` as `draw` lays it out.
+    pub fn width(&self, text: &str, style: SpanStyle) -> f32 {
+        self.layout(text, style.code).1
+    }
+
+    /// Draw `text` starting at `origin` on its baseline. Italic outlines are
+    /// sheared around the middle of lowercase letters and bold ones
+    /// overstruck, synthesizing faces the fonts lack. Emoji are never
+    /// slanted or overstruck, and color emoji keep their own colors.
+    pub fn draw(
+        &self,
+        img: &mut RgbImage,
+        text: &str,
+        style: SpanStyle,
+        (x, baseline): (f32, f32),
+        color: Rgb<u8>,
+    ) {
+        let scale = self.scale;
+        let (placed, _) = self.layout(text, style.code);
+        for glyph in placed {
+            let origin = (x.floor() + glyph.x, baseline);
+            if glyph.emoji
+                && (draw_raster(img, glyph.font, glyph.id, scale, origin)
+                    || colr::draw(img, glyph.font, glyph.id, scale, origin, color))
+            {
+                continue;
+            }
+            let (slant, strikes) = match (glyph.emoji, style.italic, style.bold) {
+                (true, ..) => (0.0, 1),
+                (false, italic, bold) => (
+                    if italic { ITALIC_SLANT } else { 0.0 },
+                    if bold { 2 } else { 1 },
+                ),
+            };
+            for dx in 0..strikes {
+                let origin = ((x + dx as f32).floor() + glyph.x, baseline);
+                draw_outline(img, glyph.font, glyph.id, scale, origin, color, slant);
+            }
+        }
+    }
+}
+
+fn draw_outline(
+    img: &mut RgbImage,
+    font: &FontRef,
+    id: GlyphId,
+    scale: PxScale,
+    (x, baseline): (f32, f32),
+    color: Rgb<u8>,
+    slant: f32,
+) {
+    let pivot = baseline - scale.y * 0.27;
+    let glyph = id.with_scale_and_position(scale, point(x, baseline));
+    let Some(outlined) = font.outline_glyph(glyph) else {
+        return;
+    };
+    let bounds = outlined.px_bounds();
+    outlined.draw(|gx, gy, coverage| {
+        let py = bounds.min.y + gy as f32;
+        let px = bounds.min.x + gx as f32 + (pivot - py) * slant;
+        let (px, py) = (px.round() as i32, py as i32);
+        if px < 0 || py < 0 || px >= img.width() as i32 || py >= img.height() as i32 {
+            return;
+        }
+        let under = img.get_pixel_mut(px as u32, py as u32);
+        let mix = |f: u8, b: u8| (b as f32 + (f as f32 - b as f32) * coverage).round() as u8;
+        *under = Rgb([
+            mix(color[0], under[0]),
+            mix(color[1], under[1]),
+            mix(color[2], under[2]),
+        ]);
+    });
+}
+
+/// Draw the PNG bitmap `font` has for `id` (CBDT or sbix color emoji),
+/// scaled from the nearest strike to the text size. Returns false when the
+/// font has no bitmap for the glyph.
+fn draw_raster(
+    img: &mut RgbImage,
+    font: &FontRef,
+    id: GlyphId,
+    scale: PxScale,
+    (x, baseline): (f32, f32),
+) -> bool {
+    let em = font.as_scaled(scale).scale_factor().vertical * font.units_per_em().unwrap_or(1000.0);
+    let Some(raster) = font.glyph_raster_image2(id, em.round() as u16) else {
+        return false;
+    };
+    if !matches!(raster.format, GlyphImageFormat::Png) {
+        return false;
+    }
+    let Ok(bitmap) = image::load_from_memory(raster.data) else {
+        return false;
+    };
+    let ratio = em / raster.pixels_per_em.max(1) as f32;
+    let width = (bitmap.width() as f32 * ratio).round().max(1.0) as u32;
+    let height = (bitmap.height() as f32 * ratio).round().max(1.0) as u32;
+    let bitmap = imageops::resize(&bitmap.to_rgba8(), width, height, FilterType::Triangle);
+
+    // The strike's origin is the bitmap's bottom-left corner, y up
+    let left = (x + raster.origin.x * ratio).round() as i64;
+    let top = (baseline - raster.origin.y * ratio).round() as i64 - height as i64;
+    for (bx, by, pixel) in bitmap.enumerate_pixels() {
+        let (px, py) = (left + bx as i64, top + by as i64);
+        if px < 0 || py < 0 || px >= img.width() as i64 || py >= img.height() as i64 {
+            continue;
+        }
+        let alpha = pixel[3] as f32 / 255.0;
+        let under = img.get_pixel_mut(px as u32, py as u32);
+        let mix = |f: u8, b: u8| (b as f32 + (f as f32 - b as f32) * alpha).round() as u8;
+        *under = Rgb([
+            mix(pixel[0], under[0]),
+            mix(pixel[1], under[1]),
+            mix(pixel[2], under[2]),
+        ]);
+    }
+    true
+}
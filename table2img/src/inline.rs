@@ -0,0 +1,428 @@
+use crate::fonts::{self, Fonts};
+use ab_glyph::{Font, ScaleFont};
+use image::{Rgb, RgbImage};
+use imageproc::drawing::draw_filled_rect_mut;
+use imageproc::rect::Rect;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Formatting of a run of cell text.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct SpanStyle {
+    pub bold: bool,
+    pub italic: bool,
+    pub code: bool,
+    pub strike: bool,
+    pub link: bool,
+    /// Text color in place of the cell's or link color
+    pub color: Option<Rgb<u8>>,
+}
+
+/// A run of cell text with a single style.
+pub struct Span {
+    pub text: String,
+    pub style: SpanStyle,
+}
+
+/// Escape a leading marker that would make pulldown-cmark read the cell as a
+/// list item, heading, block quote or rule rather than inline text.
+fn escape_block_markers(cell: &str) -> String {
+    let mut chars = cell.chars();
+    let first = chars.next();
+    let second = chars.next();
+    match first {
+        Some('#' | '>' | '-' | '+' | '=') => return format!("\\{}", cell),
+        // `* item`, a lone `*`, or a rule such as `***` / `_ _ _`
+        Some(marker @ ('*' | '_'))
+            if second.is_none_or(char::is_whitespace)
+                || cell.chars().all(|c| c == marker || c.is_whitespace()) =>
+        {
+            return format!("\\{}", cell)
+        }
+        _ => {}
+    }
+    // `1.` or `1)` followed by a space or the end starts an ordered list
+    let digits = cell.chars().take_while(|c| c.is_ascii_digit()).count();
+    if (1..=9).contains(&digits) {
+        let rest = &cell[digits..];
+        if (rest.starts_with('.') || rest.starts_with(')'))
+            && rest[1..].chars().next().is_none_or(|c| c.is_whitespace())
+        {
+            return format!("{}\\{}", &cell[..digits], rest);
+        }
+    }
+    cell.to_string()
+}
+
+/// Whether `html` is a `<br>`, `<br/>` or `<br />` tag.
+fn is_line_break(html: &str) -> bool {
+    let tag = html.trim().to_ascii_lowercase();
+    matches!(tag.as_str(), "<br>" | "<br/>" | "<br />")
+}
+
+/// Split a cell's inline markdown into styled spans: `**bold**`, `*italic*`,
+/// `` `code` ``, `~~strikethrough~~` and `[links](url)`, which keep only their
+/// text. A `<br>` becomes a `\n` in the span text.
+pub fn parse(cell: &str) -> Vec<Span> {
+    let source = escape_block_markers(cell);
+    let mut spans: Vec<Span> = Vec::new();
+    let mut style = SpanStyle::default();
+    let mut push = |text: &str, style: SpanStyle| match spans.last_mut() {
+        Some(last) if last.style == style => last.text.push_str(text),
+        _ => spans.push(Span {
+            text: text.to_string(),
+            style,
+        }),
+    };
+
+    for event in Parser::new_ext(&source, Options::ENABLE_STRIKETHROUGH) {
+        match event {
+            Event::Start(Tag::Strong) => style.bold = true,
+            Event::End(TagEnd::Strong) => style.bold = false,
+            Event::Start(Tag::Emphasis) => style.italic = true,
+            Event::End(TagEnd::Emphasis) => style.italic = false,
+            Event::Start(Tag::Strikethrough) => style.strike = true,
+            Event::End(TagEnd::Strikethrough) => style.strike = false,
+            Event::Start(Tag::Link { .. }) => style.link = true,
+            Event::End(TagEnd::Link) => style.link = false,
+            Event::Html(html) | Event::InlineHtml(html) if is_line_break(&html) => {
+                push("\n", style)
+            }
+            Event::Text(text) | Event::Html(text) | Event::InlineHtml(text) => push(&text, style),
+            Event::Code(text) => push(
+                &text,
+                SpanStyle {
+                    code: true,
+                    ..style
+                },
+            ),
+            Event::SoftBreak | Event::HardBreak => push(" ", style),
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// A single span of `cell` in `style`, for input that is not markdown.
+pub fn unformatted(cell: &str, style: SpanStyle) -> Vec<Span> {
+    vec![Span {
+        text: cell.to_string(),
+        style,
+    }]
+}
+
+/// Cell text without formatting.
+pub fn plain_text(spans: &[Span]) -> String {
+    spans.iter().map(|s| s.text.as_str()).collect()
+}
+
+type StyledGrapheme<'a> = (&'a str, SpanStyle);
+
+/// Break `spans` into lines at each `<br>` and, given `max_cells`, word-wrap
+/// each line to at most that many cells, counting emoji and other wide
+/// characters as two. Words longer than a line are split wherever they
+/// overflow, but never inside a grapheme.
+pub fn wrap(spans: &[Span], max_cells: Option<usize>) -> Vec<Vec<Span>> {
+    let graphemes: Vec<StyledGrapheme> = spans
+        .iter()
+        .flat_map(|s| s.text.graphemes(true).map(move |g| (g, s.style)))
+        .collect();
+    let mut lines = Vec::new();
+    for line in graphemes.split(|&(g, _)| g == "\n") {
+        match max_cells {
+            Some(max) => lines.extend(wrap_line(line, max.max(1))),
+            None => lines.push(line.to_vec()),
+        }
+    }
+    lines
+        .iter()
+        .map(|line| {
+            line.chunk_by(|a, b| a.1 == b.1)
+                .map(|run| Span {
+                    text: run.iter().map(|&(g, _)| g).collect(),
+                    style: run[0].1,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn width_of(graphemes: &[StyledGrapheme]) -> usize {
+    graphemes.iter().map(|&(g, _)| fonts::cells(g)).sum()
+}
+
+fn wrap_line<'a>(line: &[StyledGrapheme<'a>], max: usize) -> Vec<Vec<StyledGrapheme<'a>>> {
+    let mut lines = Vec::new();
+    let mut current: Vec<StyledGrapheme> = Vec::new();
+    // The space before the next word keeps its own style, so a link or
+    // strikethrough stays continuous across it
+    let mut space = None;
+    for token in line.chunk_by(|a, b| (a.0 == " ") == (b.0 == " ")) {
+        if token[0].0 == " " {
+            space = Some(token[0]);
+            continue;
+        }
+        if !current.is_empty() && width_of(&current) + 1 + width_of(token) > max {
+            lines.push(std::mem::take(&mut current));
+        }
+        if !current.is_empty() {
+            current.extend(space);
+        }
+        space = None;
+        for &grapheme in token {
+            let width = fonts::cells(grapheme.0);
+            if !current.is_empty() && width_of(&current) + width > max {
+                lines.push(std::mem::take(&mut current));
+            }
+            current.push(grapheme);
+        }
+    }
+    lines.push(current);
+    lines
+}
+
+/// Fonts and colors for drawing spans.
+pub struct SpanPainter<'a> {
+    pub fonts: &'a Fonts<'a>,
+    pub link: Rgb<u8>,
+    pub code_bg: Rgb<u8>,
+}
+
+impl SpanPainter<'_> {
+    /// Padding on each side of a code span's pill.
+    fn code_pad(&self) -> f32 {
+        (self.fonts.cell_width() / 3.0).round()
+    }
+
+    fn text_width(&self, text: &str, style: SpanStyle) -> f32 {
+        self.fonts.width(text, style)
+    }
+
+    /// Width of `spans` as drawn, including code pills.
+    pub fn width(&self, spans: &[Span]) -> f32 {
+        spans
+            .iter()
+            .map(|span| {
+                let text = self.text_width(&span.text, span.style);
+                if span.style.code {
+                    text + self.code_pad() * 2.0
+                } else {
+                    text
+                }
+            })
+            .sum()
+    }
+
+    /// Draw `spans` with their top-left corner at (`x`, `y`), in `color`
+    /// unless a span is a link or has its own color.
+    pub fn draw(&self, img: &mut RgbImage, spans: &[Span], x: f32, y: f32, color: Rgb<u8>) {
+        let scale = self.fonts.scale;
+        let ascent = self.fonts.text.as_scaled(scale).ascent();
+        let mut x = x;
+        for span in spans {
+            let style = span.style;
+            let text_width = self.text_width(&span.text, style);
+            if style.code {
+                let pad = self.code_pad();
+                let rect = Rect::at(x as i32, y as i32 - 2)
+                    .of_size((text_width + pad * 2.0) as u32, scale.y as u32 + 4);
+                draw_filled_rect_mut(img, rect, self.code_bg);
+                x += pad;
+            }
+
+            let color = match style.color {
+                Some(color) => color,
+                None if style.link => self.link,
+                None => color,
+            };
+            // Code spans sit on the same baseline as the text around them
+            let baseline = y.floor() + ascent;
+            self.fonts
+                .draw(img, &span.text, style, (x, baseline), color);
+
+            if style.link {
+                let rect =
+                    Rect::at(x as i32, (baseline + 2.0) as i32).of_size(text_width as u32, 1);
+                draw_filled_rect_mut(img, rect, color);
+            }
+            if style.strike {
+                let strike_y = baseline - scale.y * 0.27;
+                let rect = Rect::at(x as i32, strike_y as i32).of_size(text_width as u32, 1);
+                draw_filled_rect_mut(img, rect, color);
+            }
+
+            x += text_width;
+            if style.code {
+                x += self.code_pad();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ab_glyph::{FontRef, PxScale};
+
+    const MONO: &[u8] = include_bytes!("../assets/JetBrainsMono-Regular.ttf");
+    const SANS: &[u8] = include_bytes!("../assets/DejaVuSans.ttf");
+
+    /// Fonts at 20px with `text` as the cell font.
+    fn fonts(text: &'static [u8]) -> Fonts<'static> {
+        let font = |data| FontRef::try_from_slice(data).unwrap();
+        Fonts::new(font(text), font(MONO), None, PxScale::from(20.0))
+    }
+
+    fn wrapped(cell: &str, max: usize) -> Vec<String> {
+        wrap(&unformatted(cell, SpanStyle::default()), Some(max))
+            .iter()
+            .map(|line| plain_text(line))
+            .collect()
+    }
+
+    /// Text of each span with a `*` suffix when bold and `_` when italic.
+    fn styled(cell: &str) -> Vec<String> {
+        parse(cell)
+            .iter()
+            .map(|span| {
+                let mut text = span.text.clone();
+                if span.style.bold {
+                    text.push('*');
+                }
+                if span.style.italic {
+                    text.push('_');
+                }
+                text
+            })
+            .collect()
+    }
+
+    #[test]
+    fn block_markers_stay_text() {
+        for cell in [
+            "- item",
+            "+ item",
+            "* item",
+            "*",
+            "# heading",
+            "## 2",
+            "> quote",
+        ] {
+            assert_eq!(plain_text(&parse(cell)), cell);
+        }
+        for cell in ["---", "***", "_ _ _", "==="] {
+            assert_eq!(plain_text(&parse(cell)), cell);
+        }
+        assert_eq!(escape_block_markers("- item"), "\\- item");
+        assert_eq!(escape_block_markers("plain"), "plain");
+    }
+
+    #[test]
+    fn ordered_list_markers_stay_text() {
+        for cell in ["1. first", "2) second", "10.", "123456789. many"] {
+            assert_eq!(plain_text(&parse(cell)), cell);
+        }
+        assert_eq!(escape_block_markers("1. first"), "1\\. first");
+        // Not list markers: no space after, or too many digits for one
+        assert_eq!(escape_block_markers("1.5"), "1.5");
+        assert_eq!(escape_block_markers("1234567890. x"), "1234567890. x");
+    }
+
+    #[test]
+    fn inline_formatting_survives_escaping() {
+        assert_eq!(styled("*emphasis* here"), ["emphasis_", " here"]);
+        assert_eq!(styled("**bold** text"), ["bold*", " text"]);
+        assert_eq!(styled("- **done**"), ["- ", "done*"]);
+        assert_eq!(styled("1. *one*"), ["1. ", "one_"]);
+        assert_eq!(plain_text(&parse("a<br>b")), "a\nb");
+    }
+
+    #[test]
+    fn wrap_at_word_boundaries() {
+        assert_eq!(
+            wrapped("the quick brown fox", 10),
+            ["the quick", "brown fox"]
+        );
+        assert_eq!(wrapped("short", 10), ["short"]);
+        // Words longer than a line are split where they overflow
+        assert_eq!(wrapped("abcdefghij xy", 4), ["abcd", "efgh", "ij", "xy"]);
+    }
+
+    #[test]
+    fn wrap_keeps_styles_and_line_breaks() {
+        let lines = wrap(&parse("**bold words** then<br>next"), Some(10));
+        let text: Vec<String> = lines.iter().map(|line| plain_text(line)).collect();
+        assert_eq!(text, ["bold words", "then", "next"]);
+        assert!(lines[0].iter().all(|span| span.style.bold));
+        assert!(lines[1].iter().all(|span| !span.style.bold));
+        // Without a width, only line breaks split the cell
+        assert_eq!(wrap(&parse("a b<br>c"), None).len(), 2);
+    }
+
+    #[test]
+    fn monospace_widths_follow_cells() {
+        let fonts = fonts(MONO);
+        let cell = fonts.cell_width();
+        let width = |text| fonts.width(text, SpanStyle::default());
+        assert_eq!(width("abc"), 3.0 * cell);
+        assert_eq!(width("ok ✅"), 5.0 * cell);
+        assert_eq!(width("👨‍👩‍👧"), 2.0 * cell);
+        assert_eq!(width(""), 0.0);
+    }
+
+    #[test]
+    fn proportional_widths_use_glyph_advances() {
+        let fonts = fonts(SANS);
+        let width = |text, code| {
+            let style = SpanStyle {
+                code,
+                ..SpanStyle::default()
+            };
+            fonts.width(text, style)
+        };
+        let sans = fonts.text.as_scaled(fonts.scale);
+        let advance = |c| sans.h_advance(sans.glyph_id(c));
+        assert_eq!(width("i", false), advance('i'));
+        assert!(width("iii", false) < width("MMM", false));
+        // Code spans stay monospace
+        assert_eq!(width("iii", true), width("MMM", true));
+        // Emoji still take two cells of the code font
+        assert_eq!(width("✅", false), 2.0 * fonts.cell_width());
+    }
+
+    #[test]
+    fn code_pills_add_padding() {
+        let fonts = fonts(SANS);
+        let painter = SpanPainter {
+            fonts: &fonts,
+            link: Rgb([0, 0, 255]),
+            code_bg: Rgb([0, 0, 0]),
+        };
+        let text = fonts.width("x", SpanStyle::default());
+        let code = fonts.width(
+            "x",
+            SpanStyle {
+                code: true,
+                ..SpanStyle::default()
+            },
+        );
+        let spans = parse("x `x`");
+        let space = fonts.width(" ", SpanStyle::default());
+        assert_eq!(
+            painter.width(&spans),
+            text + space + code + 2.0 * painter.code_pad()
+        );
+    }
+
+    #[test]
+    fn emoji_wrap_as_two_cells() {
+        assert_eq!(wrapped("ok ✅ done", 5), ["ok ✅", "done"]);
+        assert_eq!(wrapped("✅✅✅", 5), ["✅✅", "✅"]);
+    }
+
+    #[test]
+    fn graphemes_are_not_split() {
+        assert_eq!(wrapped("⚠️⚠️", 3), ["⚠️", "⚠️"]);
+        assert_eq!(wrapped("👨‍👩‍👧x", 2), ["👨‍👩‍👧", "x"]);
+    }
+}
@@ -0,0 +1,160 @@
+use crate::table::{Align, Table};
+use serde_json::{Deserializer, Map, Value};
+
+/// JSON type of a cell, which the renderer styles differently.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    /// A string, or an object or array shown as compact JSON
+    Text,
+    Number,
+    Bool,
+    Null,
+    /// The record has no value at the column's path
+    Missing,
+}
+
+/// A table read from JSON, with the type of each cell in `rows`.
+pub struct JsonTable {
+    pub table: Table,
+    pub kinds: Vec<Vec<Kind>>,
+}
+
+/// Value at a dotted `path` such as `user.name` or `items.0.id`. A key that
+/// itself contains dots is matched whole first.
+fn lookup<'a>(record: &'a Map<String, Value>, path: &str) -> Option<&'a Value> {
+    if let Some(value) = record.get(path) {
+        return Some(value);
+    }
+    let mut segments = path.split('.');
+    let mut value = record.get(segments.next()?)?;
+    for segment in segments {
+        value = match value {
+            Value::Object(map) => map.get(segment)?,
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    Some(value)
+}
+
+fn cell(value: Option<&Value>) -> (String, Kind) {
+    match value {
+        None => (String::new(), Kind::Missing),
+        Some(Value::Null) => ("null".to_string(), Kind::Null),
+        Some(Value::Bool(b)) => (b.to_string(), Kind::Bool),
+        Some(Value::Number(n)) => (n.to_string(), Kind::Number),
+        Some(Value::String(s)) => (s.clone(), Kind::Text),
+        Some(nested) => (nested.to_string(), Kind::Text),
+    }
+}
+
+/// Read a JSON array of objects, or JSON Lines with one object per line.
+/// Columns are `columns` when given, as dotted paths into each record, and
+/// otherwise the union of the records' keys in first-seen order. Columns
+/// holding only numbers (and nulls) are right-aligned.
+pub fn parse_json(input: &str, columns: &[String]) -> Result<JsonTable, String> {
+    let mut values = Vec::new();
+    for value in Deserializer::from_str(input).into_iter::<Value>() {
+        values.push(value.map_err(|e| format!("Invalid JSON: {}", e))?);
+    }
+    if let [Value::Array(items)] = values.as_mut_slice() {
+        values = std::mem::take(items);
+    }
+    let records: Vec<Map<String, Value>> = values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| match value {
+            Value::Object(record) => Ok(record),
+            other => Err(format!(
+                "Record {} is not an object: {}",
+                i + 1,
+                other.to_string().chars().take(40).collect::<String>()
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let headers: Vec<String> = if columns.is_empty() {
+        let mut keys: Vec<String> = Vec::new();
+        for key in records.iter().flat_map(|r| r.keys()) {
+            if !keys.contains(key) {
+                keys.push(key.clone());
+            }
+        }
+        keys
+    } else {
+        columns.iter().map(|c| c.trim().to_string()).collect()
+    };
+    if headers.is_empty() {
+        return Err("No records with fields in input".to_string());
+    }
+
+    let (rows, kinds): (Vec<Vec<String>>, Vec<Vec<Kind>>) = records
+        .iter()
+        .map(|record| headers.iter().map(|h| cell(lookup(record, h))).unzip())
+        .unzip();
+
+    let mut warnings = Vec::new();
+    let mut aligns = Vec::new();
+    for (j, header) in headers.iter().enumerate() {
+        let column = || kinds.iter().map(move |row| row[j]);
+        if column().all(|k| k == Kind::Missing) {
+            warnings.push(format!("no record has a value for column '{}'", header));
+        }
+        let numeric = column().any(|k| k == Kind::Number)
+            && column().all(|k| matches!(k, Kind::Number | Kind::Null | Kind::Missing));
+        aligns.push(numeric.then_some(Align::Right));
+    }
+
+    Ok(JsonTable {
+        table: Table {
+            headers,
+            aligns,
+            rows,
+            warnings,
+        },
+        kinds,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(row: &[&str]) -> Vec<String> {
+        row.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn array_with_key_union() {
+        let json = parse_json(r#"[{"b": 1, "a": "x"}, {"c": null, "a": true}]"#, &[]).unwrap();
+        assert_eq!(json.table.headers, cells(&["b", "a", "c"]));
+        assert_eq!(
+            json.table.rows,
+            vec![cells(&["1", "x", ""]), cells(&["", "true", "null"])]
+        );
+        assert_eq!(json.kinds[1], vec![Kind::Missing, Kind::Bool, Kind::Null]);
+        assert_eq!(json.table.aligns, vec![Some(Align::Right), None, None]);
+    }
+
+    #[test]
+    fn json_lines_with_columns() {
+        let input = "{\"user\": {\"name\": \"ann\"}, \"tags\": [\"a\", \"b\"]}\n\
+                     {\"user\": {\"name\": \"bob\"}, \"tags\": []}\n";
+        let columns = cells(&["tags.0", "user.name", "tags"]);
+        let json = parse_json(input, &columns).unwrap();
+        assert_eq!(json.table.headers, columns);
+        assert_eq!(
+            json.table.rows,
+            vec![
+                cells(&["a", "ann", "[\"a\",\"b\"]"]),
+                cells(&["", "bob", "[]"])
+            ]
+        );
+    }
+
+    #[test]
+    fn non_object_records_are_rejected() {
+        assert!(parse_json("[1, 2]", &[]).is_err());
+        assert!(parse_json("{\"a\": 1}\n{\"a\": ", &[]).is_err());
+    }
+}
@@ -1,21 +1,73 @@
-use ab_glyph::{Font, FontRef, PxScale, ScaleFont};
-use clap::Parser;
+mod colr;
+mod delimited;
+mod fonts;
+mod inline;
+mod json;
+mod rules;
+mod table;
+
+use ab_glyph::{FontRef, PxScale};
+use clap::{Parser, ValueEnum};
+use fonts::Fonts;
 use image::{Rgb, RgbImage};
-use imageproc::drawing::{draw_filled_rect_mut, draw_line_segment_mut, draw_text_mut};
+use imageproc::drawing::{draw_filled_rect_mut, draw_line_segment_mut};
 use imageproc::rect::Rect;
+use inline::{Span, SpanPainter, SpanStyle};
+use json::Kind;
+use rules::{CellStyle, Rule};
 use std::fs;
+use std::path::Path;
+use table::{Align, Table};
 
 #[derive(Parser)]
-#[command(name = "table2img", about = "Render a markdown table to a PNG image")]
+#[command(
+    name = "table2img",
+    about = "Render a markdown, CSV, TSV or JSON table to a PNG image"
+)]
 struct Cli {
-    /// Input file containing a markdown table
+    /// Input file: a markdown document with tables, CSV, TSV or JSON
     #[arg(short, long)]
     input: String,
 
-    /// Output PNG path
+    /// Output PNG path; numbered when the input has several tables
     #[arg(short, long)]
     output: String,
 
+    /// Input format (defaults to csv for .csv inputs, tsv for .tsv and .tab,
+    /// json for .json, .jsonl and .ndjson, md otherwise)
+    #[arg(long, value_enum)]
+    format: Option<InputFormat>,
+
+    /// Field delimiter for CSV input, e.g. ';' (default ',', or tab for TSV)
+    #[arg(long)]
+    delimiter: Option<char>,
+
+    /// CSV/TSV input has no header record: every record is a row, and the
+    /// table is drawn without a header row
+    #[arg(long)]
+    no_header: bool,
+
+    /// JSON fields to show, in order, as comma-separated dotted paths (e.g.
+    /// name,stats.mean,tags.0); defaults to every top-level key
+    #[arg(long, value_delimiter = ',')]
+    columns: Vec<String>,
+
+    /// Render only the Nth table of the input, counting from 1. Otherwise
+    /// every table is rendered, to numbered outputs (out-1.png, out-2.png,
+    /// ...) when there are several
+    #[arg(long, value_name = "N")]
+    index: Option<usize>,
+
+    /// TTF or OTF font for cell text, monospace or proportional (defaults to
+    /// the embedded JetBrains Mono, which code spans always use)
+    #[arg(long)]
+    font: Option<String>,
+
+    /// Color emoji font with COLR layers or CBDT/sbix bitmaps, such as Noto
+    /// Color Emoji or Twemoji, tried before the bundled emoji fonts
+    #[arg(long, value_name = "FONT")]
+    emoji_font: Option<String>,
+
     /// Font size in pixels
     #[arg(long, default_value = "24")]
     font_size: f32,
@@ -23,6 +75,41 @@ struct Cli {
     /// Theme: "dark" or "light"
     #[arg(long, default_value = "dark")]
     theme: String,
+
+    /// Right-align columns whose cells are all numbers, unless the separator
+    /// row gives the column an alignment
+    #[arg(long)]
+    align_numbers: bool,
+
+    /// Word-wrap cells wider than this many characters, counting emoji as
+    /// two. Either a width for every column, or COLUMN=WIDTH for one column
+    /// by 1-based number or header text; repeatable
+    #[arg(long, value_name = "[COLUMN=]CHARS")]
+    max_col_width: Vec<String>,
+
+    /// Style matching data cells, as comma-separated KEY=VALUE fields:
+    /// col=COLUMN (number or header text; default every column), match=REGEX,
+    /// lt/le/gt/ge/eq/ne=NUMBER, and fg=#RRGGBB, bg=#RRGGBB or bold.
+    /// Repeatable; later rules override earlier ones
+    #[arg(long, value_name = "RULE")]
+    rule: Vec<String>,
+
+    /// File of --rule values, one per line, applied before any --rule;
+    /// blank lines and lines starting with # are skipped
+    #[arg(long, value_name = "FILE")]
+    rules: Option<String>,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum InputFormat {
+    /// Every GitHub Flavored Markdown table in the input
+    Md,
+    /// Comma-separated values with RFC 4180 quoting
+    Csv,
+    /// Tab-separated values
+    Tsv,
+    /// An array of objects, or JSON Lines with one object per line
+    Json,
 }
 
 struct Theme {
@@ -32,6 +119,10 @@ struct Theme {
     text: Rgb<u8>,
     header_text: Rgb<u8>,
     border: Rgb<u8>,
+    link: Rgb<u8>,
+    code_bg: Rgb<u8>,
+    null: Rgb<u8>,
+    boolean: Rgb<u8>,
 }
 
 fn dark_theme() -> Theme {
@@ -42,6 +133,10 @@ fn dark_theme() -> Theme {
         text: Rgb([192, 197, 206]),
         header_text: Rgb([235, 203, 139]),
         border: Rgb([65, 73, 89]),
+        link: Rgb([143, 161, 179]),
+        code_bg: Rgb([55, 61, 74]),
+        null: Rgb([101, 115, 126]),
+        boolean: Rgb([180, 142, 173]),
     }
 }
 
@@ -53,44 +148,92 @@ fn light_theme() -> Theme {
         text: Rgb([51, 51, 51]),
         header_text: Rgb([31, 31, 31]),
         border: Rgb([200, 200, 200]),
+        link: Rgb([3, 102, 214]),
+        code_bg: Rgb([234, 236, 239]),
+        null: Rgb([150, 150, 150]),
+        boolean: Rgb([111, 66, 193]),
     }
 }
 
-fn parse_table(input: &str) -> (Vec<String>, Vec<Vec<String>>) {
-    let lines: Vec<&str> = input
-        .lines()
-        .map(|l| l.trim())
-        .filter(|l| !l.is_empty())
-        .collect();
-
-    let mut headers = Vec::new();
-    let mut rows = Vec::new();
+/// Alignment for each of `num_cols` columns: the separator row's marker,
+/// else right for all-numeric columns when `align_numbers` is set, else left.
+fn column_aligns(
+    markers: &[Option<Align>],
+    rows: &[Vec<Vec<Span>>],
+    num_cols: usize,
+    align_numbers: bool,
+) -> Vec<Align> {
+    (0..num_cols)
+        .map(|j| {
+            markers.get(j).copied().flatten().unwrap_or_else(|| {
+                let mut cells = rows
+                    .iter()
+                    .filter_map(|r| r.get(j))
+                    .map(|c| inline::plain_text(c))
+                    .filter(|c| !c.is_empty())
+                    .peekable();
+                if align_numbers
+                    && cells.peek().is_some()
+                    && cells.all(|c| rules::number(&c).is_some())
+                {
+                    Align::Right
+                } else {
+                    Align::Left
+                }
+            })
+        })
+        .collect()
+}
 
-    for (i, line) in lines.iter().enumerate() {
-        // Skip separator lines (e.g. |---|---|)
-        let stripped = line.replace('|', "").replace('-', "").replace(':', "").trim().to_string();
-        if stripped.is_empty() {
+/// Wrap width for each column from the `--max-col-width` specs, where a
+/// per-column spec overrides a global one.
+fn max_widths(specs: &[String], headers: &[String]) -> Vec<Option<usize>> {
+    let mut global = None;
+    let mut widths = vec![None; headers.len()];
+    for spec in specs {
+        let (column, chars) = match spec.rsplit_once('=') {
+            Some((column, chars)) => (Some(column.trim()), chars),
+            None => (None, spec.as_str()),
+        };
+        let chars = match chars.trim().parse::<usize>() {
+            Ok(chars) if chars > 0 => chars,
+            _ => {
+                eprintln!(
+                    "Invalid --max-col-width '{}': width must be a positive number",
+                    spec
+                );
+                std::process::exit(1);
+            }
+        };
+        let Some(column) = column else {
+            global = Some(chars);
             continue;
-        }
-
-        let cells: Vec<String> = line
-            .trim_matches('|')
-            .split('|')
-            .map(|c| c.trim().to_string())
-            .collect();
-
-        if headers.is_empty() {
-            headers = cells;
-        } else {
-            rows.push(cells);
-        }
+        };
+        let index = match column.parse::<usize>() {
+            Ok(n) if (1..=headers.len()).contains(&n) => Some(n - 1),
+            Ok(_) => None,
+            Err(_) => headers.iter().position(|h| h == column),
+        };
+        // Tables in one document differ, so a missing column only warns
+        let Some(index) = index else {
+            eprintln!(
+                "Warning: --max-col-width '{}': no column '{}'",
+                spec, column
+            );
+            continue;
+        };
+        widths[index] = Some(chars);
     }
-
-    (headers, rows)
+    widths.iter().map(|w| w.or(global)).collect()
 }
 
-fn measure_text_width(text: &str, char_advance: f32) -> f32 {
-    text.chars().count() as f32 * char_advance
+/// X position of text `text_width` wide in a cell starting at `x`.
+fn aligned_x(align: Align, x: f32, col_width: f32, text_width: f32, pad: f32) -> f32 {
+    match align {
+        Align::Left => x + pad,
+        Align::Center => x + (col_width - text_width) / 2.0,
+        Align::Right => x + col_width - pad - text_width,
+    }
 }
 
 fn main() {
@@ -105,11 +248,92 @@ fn main() {
         fs::read_to_string(&cli.input).expect("Failed to read input file")
     };
 
-    let (headers, rows) = parse_table(&content);
-    if headers.is_empty() {
+    let format = cli.format.unwrap_or_else(|| {
+        let ext = Path::new(&cli.input)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("csv") => InputFormat::Csv,
+            Some("tsv" | "tab") => InputFormat::Tsv,
+            Some("json" | "jsonl" | "ndjson") => InputFormat::Json,
+            _ => InputFormat::Md,
+        }
+    });
+    let is_delimited = matches!(format, InputFormat::Csv | InputFormat::Tsv);
+    if !is_delimited && (cli.delimiter.is_some() || cli.no_header) {
+        eprintln!("--delimiter and --no-header only apply to CSV and TSV input");
+        std::process::exit(1);
+    }
+    if format != InputFormat::Json && !cli.columns.is_empty() {
+        eprintln!("--columns only applies to JSON input");
+        std::process::exit(1);
+    }
+    let delimiter = match cli.delimiter {
+        Some(c) if c.is_ascii() => c as u8,
+        Some(c) => {
+            eprintln!(
+                "Invalid --delimiter '{}': must be a single ASCII character",
+                c
+            );
+            std::process::exit(1);
+        }
+        None if format == InputFormat::Tsv => b'\t',
+        None => b',',
+    };
+
+    // Every table in the input, with the type of each cell for JSON
+    let tables: Vec<(Table, Vec<Vec<Kind>>)> = match format {
+        InputFormat::Md => table::parse_tables(&content)
+            .into_iter()
+            .map(|t| (t, Vec::new()))
+            .collect(),
+        InputFormat::Csv | InputFormat::Tsv => {
+            delimited::parse_delimited(&content, delimiter, !cli.no_header)
+                .map(|t| (t, Vec::new()))
+                .into_iter()
+                .collect()
+        }
+        InputFormat::Json => match json::parse_json(&content, &cli.columns) {
+            Ok(json) => vec![(json.table, json.kinds)],
+            Err(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        },
+    };
+    if tables.is_empty() {
         eprintln!("No table found in input");
         std::process::exit(1);
     }
+    let selected: Vec<usize> = match cli.index {
+        Some(n) if (1..=tables.len()).contains(&n) => vec![n - 1],
+        Some(n) => {
+            eprintln!(
+                "--index {} is out of range: the input has {} table{}",
+                n,
+                tables.len(),
+                if tables.len() == 1 { "" } else { "s" }
+            );
+            std::process::exit(1);
+        }
+        None => (0..tables.len()).collect(),
+    };
+
+    let mut rules = Vec::new();
+    if let Some(path) = &cli.rules {
+        let input = fs::read_to_string(path).expect("Failed to read rules file");
+        rules = rules::parse_file(&input).unwrap_or_else(|e| {
+            eprintln!("Invalid rules file {}: {}", path, e);
+            std::process::exit(1);
+        });
+    }
+    for spec in &cli.rule {
+        rules.push(Rule::parse(spec).unwrap_or_else(|e| {
+            eprintln!("Invalid --rule '{}': {}", spec, e);
+            std::process::exit(1);
+        }));
+    }
 
     let theme = if cli.theme == "light" {
         light_theme()
@@ -117,33 +341,156 @@ fn main() {
         dark_theme()
     };
 
-    let font_data = include_bytes!("../assets/JetBrainsMono-Regular.ttf");
-    let font = FontRef::try_from_slice(font_data).expect("Failed to load font");
-    let scale = PxScale::from(cli.font_size);
-    let scaled_font = font.as_scaled(scale);
+    let mono_data = include_bytes!("../assets/JetBrainsMono-Regular.ttf");
+    let mono = FontRef::try_from_slice(mono_data).expect("Failed to load font");
+    let read_font = |path: &String| fs::read(path).expect("Failed to read font file");
+    let font_data = cli.font.as_ref().map(read_font);
+    let emoji_data = cli.emoji_font.as_ref().map(read_font);
+    let font = match (&cli.font, &font_data) {
+        (Some(path), Some(data)) => load_font(path, data),
+        _ => mono.clone(),
+    };
+    let emoji = cli.emoji_font.as_ref().zip(emoji_data.as_ref());
+    let emoji = emoji.map(|(path, data)| load_font(path, data));
+    let fonts = Fonts::new(font, mono, emoji, PxScale::from(cli.font_size));
+    let painter = SpanPainter {
+        fonts: &fonts,
+        link: theme.link,
+        code_bg: theme.code_bg,
+    };
 
-    let glyph_id = font.glyph_id('M');
-    let char_advance = scaled_font.h_advance(glyph_id);
+    for &i in &selected {
+        let (table, kinds) = &tables[i];
+        for warning in &table.warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        let img = render_table(&cli, format, &theme, &painter, &rules, table, kinds);
+        // A document with several tables gets one image per table
+        let path = if selected.len() > 1 {
+            numbered_path(&cli.output, i + 1)
+        } else {
+            cli.output.clone()
+        };
+        img.save(&path).expect("Failed to save image");
+        println!("{}", path);
+    }
+}
+
+/// Draw `table`, whose cells have the JSON types in `kinds` if any.
+fn render_table(
+    cli: &Cli,
+    format: InputFormat,
+    theme: &Theme,
+    painter: &SpanPainter,
+    rules: &[Rule],
+    table: &Table,
+    kinds: &[Vec<Kind>],
+) -> RgbImage {
+    let show_header = !cli.no_header;
+    // Column widths are measured on the rendered text, without markup. CSV,
+    // TSV and JSON cells are plain text, with JSON nulls and booleans set
+    // apart from strings and numbers.
+    let parse_cell = |cell: &String, kind: Option<Kind>| {
+        let style = match kind {
+            Some(Kind::Null) => SpanStyle {
+                italic: true,
+                color: Some(theme.null),
+                ..SpanStyle::default()
+            },
+            Some(Kind::Bool) => SpanStyle {
+                color: Some(theme.boolean),
+                ..SpanStyle::default()
+            },
+            _ => SpanStyle::default(),
+        };
+        match format {
+            InputFormat::Md => inline::parse(cell),
+            InputFormat::Csv | InputFormat::Tsv | InputFormat::Json => {
+                inline::unformatted(cell, style)
+            }
+        }
+    };
+    let headers: Vec<Vec<Span>> = table.headers.iter().map(|h| parse_cell(h, None)).collect();
+    let mut rows: Vec<Vec<Vec<Span>>> = table
+        .rows
+        .iter()
+        .enumerate()
+        .map(|(i, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(j, cell)| parse_cell(cell, kinds.get(i).map(|kinds| kinds[j])))
+                .collect()
+        })
+        .collect();
 
     let num_cols = headers.len();
+    let aligns = column_aligns(&table.aligns, &rows, num_cols, cli.align_numbers);
+    let header_names: Vec<String> = headers.iter().map(|h| inline::plain_text(h)).collect();
+    let limits = max_widths(&cli.max_col_width, &header_names);
+
+    // Conditional styles, matched against each data cell's text
+    let rule_columns: Vec<_> = rules
+        .iter()
+        .map(|r| r.column_index(&header_names))
+        .collect();
+    for (rule, column) in rules.iter().zip(&rule_columns) {
+        if let (Some(name), None) = (&rule.column, column) {
+            eprintln!("Warning: rule for column '{}': no such column", name);
+        }
+    }
+    let cell_styles: Vec<Vec<CellStyle>> = rows
+        .iter_mut()
+        .map(|row| {
+            row.iter_mut()
+                .enumerate()
+                .map(|(j, cell)| {
+                    let text = inline::plain_text(cell);
+                    let style = rules::cell_style(rules, &rule_columns, j, &text);
+                    for span in cell.iter_mut() {
+                        span.style.color = style.fg.or(span.style.color);
+                        span.style.bold |= style.bold;
+                    }
+                    style
+                })
+                .collect()
+        })
+        .collect();
+
+    // Each cell becomes its lines after `<br>` breaks and wrapping
+    let wrap_row = |row: &[Vec<Span>]| -> Vec<Vec<Vec<Span>>> {
+        row.iter()
+            .zip(&limits)
+            .map(|(cell, &limit)| inline::wrap(cell, limit))
+            .collect()
+    };
+    let headers = wrap_row(&headers);
+    let rows: Vec<Vec<Vec<Vec<Span>>>> = rows.iter().map(|row| wrap_row(row)).collect();
+
+    // Rows as drawn, top to bottom
+    let header_rows = usize::from(show_header);
+    let table_rows: Vec<&Vec<Vec<Vec<Span>>>> = show_header
+        .then_some(&headers)
+        .into_iter()
+        .chain(&rows)
+        .collect();
+
     let cell_pad_x: f32 = 20.0;
     let cell_pad_y: f32 = 12.0;
-    let row_height = cli.font_size + cell_pad_y * 2.0;
+    let line_height = (cli.font_size * 1.4).round();
     let outer_pad: f32 = 16.0;
 
-    // Calculate column widths
-    let mut col_widths: Vec<f32> = headers
-        .iter()
-        .map(|h| measure_text_width(h, char_advance))
-        .collect();
+    // A row is as tall as its cell with the most lines
+    let row_height = |cells: &[Vec<Vec<Span>>]| {
+        let lines = cells.iter().map(Vec::len).max().unwrap_or(1).max(1);
+        cli.font_size + (lines - 1) as f32 * line_height + cell_pad_y * 2.0
+    };
 
-    for row in &rows {
+    // Calculate column widths
+    let mut col_widths = vec![0.0f32; num_cols];
+    for row in &table_rows {
         for (j, cell) in row.iter().enumerate() {
-            if j < col_widths.len() {
-                let w = measure_text_width(cell, char_advance);
-                if w > col_widths[j] {
-                    col_widths[j] = w;
-                }
+            for line in cell {
+                col_widths[j] = col_widths[j].max(painter.width(line));
             }
         }
     }
@@ -153,39 +500,55 @@ fn main() {
         *w += cell_pad_x * 2.0;
     }
 
+    // Top of each row, and the table's bottom edge
+    let mut row_tops = vec![outer_pad];
+    for row in &table_rows {
+        let top = row_tops[row_tops.len() - 1];
+        row_tops.push(top + row_height(row));
+    }
+
     let total_width = col_widths.iter().sum::<f32>() + outer_pad * 2.0;
-    let total_rows = 1 + rows.len(); // header + data rows
-    let total_height = total_rows as f32 * row_height + outer_pad * 2.0;
+    let total_height = row_tops[row_tops.len() - 1] + outer_pad;
 
     let img_w = total_width.ceil() as u32;
     let img_h = total_height.ceil() as u32;
 
     let mut img = RgbImage::from_pixel(img_w, img_h, theme.bg);
+    let row_rect = |i: usize| {
+        Rect::at(outer_pad as i32, row_tops[i] as i32).of_size(
+            total_width as u32 - outer_pad as u32 * 2,
+            (row_tops[i + 1] - row_tops[i]) as u32,
+        )
+    };
 
     // Draw header background
-    draw_filled_rect_mut(
-        &mut img,
-        Rect::at(outer_pad as i32, outer_pad as i32)
-            .of_size(total_width as u32 - outer_pad as u32 * 2, row_height as u32),
-        theme.header_bg,
-    );
+    if show_header {
+        draw_filled_rect_mut(&mut img, row_rect(0), theme.header_bg);
+    }
 
     // Draw alternating row backgrounds
     for i in 0..rows.len() {
         if i % 2 == 1 {
-            let y = outer_pad + (i as f32 + 1.0) * row_height;
-            draw_filled_rect_mut(
-                &mut img,
-                Rect::at(outer_pad as i32, y as i32)
-                    .of_size(total_width as u32 - outer_pad as u32 * 2, row_height as u32),
-                theme.alt_row_bg,
-            );
+            draw_filled_rect_mut(&mut img, row_rect(i + header_rows), theme.alt_row_bg);
+        }
+    }
+
+    // Draw rule backgrounds over the row backgrounds
+    for (i, styles) in cell_styles.iter().enumerate() {
+        let row = row_rect(i + header_rows);
+        let mut x = outer_pad;
+        for (j, style) in styles.iter().enumerate() {
+            if let Some(bg) = style.bg {
+                let rect =
+                    Rect::at(x as i32, row.top()).of_size(col_widths[j] as u32, row.height());
+                draw_filled_rect_mut(&mut img, rect, bg);
+            }
+            x += col_widths[j];
         }
     }
 
     // Draw horizontal lines
-    for i in 0..=total_rows {
-        let y = outer_pad + i as f32 * row_height;
+    for &y in &row_tops {
         draw_line_segment_mut(
             &mut img,
             (outer_pad, y),
@@ -195,57 +558,84 @@ fn main() {
     }
 
     // Draw vertical lines
+    let top_y = row_tops[0];
+    let bot_y = row_tops[row_tops.len() - 1];
     let mut x = outer_pad;
-    for i in 0..=num_cols {
-        let top_y = outer_pad;
-        let bot_y = outer_pad + total_rows as f32 * row_height;
+    draw_line_segment_mut(&mut img, (x, top_y), (x, bot_y), theme.border);
+    for w in &col_widths {
+        x += w;
         draw_line_segment_mut(&mut img, (x, top_y), (x, bot_y), theme.border);
-        if i < num_cols {
-            x += col_widths[i];
-        }
     }
 
-    // Draw header text
-    let text_y_offset = cell_pad_y;
-    let mut x = outer_pad;
-    for (j, header) in headers.iter().enumerate() {
-        let tx = x + cell_pad_x;
-        let ty = outer_pad + text_y_offset;
-        draw_text_mut(
-            &mut img,
-            theme.header_text,
-            tx as i32,
-            ty as i32,
-            scale,
-            &font,
-            header,
-        );
-        if j < col_widths.len() {
-            x += col_widths[j];
-        }
-    }
-
-    // Draw data rows
-    for (i, row) in rows.iter().enumerate() {
+    // Draw header and data text, top-aligned in each cell
+    for (i, row) in table_rows.iter().enumerate() {
+        let color = if i < header_rows {
+            theme.header_text
+        } else {
+            theme.text
+        };
         let mut x = outer_pad;
         for (j, cell) in row.iter().enumerate() {
-            let tx = x + cell_pad_x;
-            let ty = outer_pad + (i as f32 + 1.0) * row_height + text_y_offset;
-            draw_text_mut(
-                &mut img,
-                theme.text,
-                tx as i32,
-                ty as i32,
-                scale,
-                &font,
-                cell,
-            );
-            if j < col_widths.len() {
-                x += col_widths[j];
+            for (k, line) in cell.iter().enumerate() {
+                let width = painter.width(line);
+                let tx = aligned_x(aligns[j], x, col_widths[j], width, cell_pad_x);
+                let ty = row_tops[i] + cell_pad_y + k as f32 * line_height;
+                painter.draw(&mut img, line, tx, ty, color);
             }
+            x += col_widths[j];
         }
     }
 
-    img.save(&cli.output).expect("Failed to save image");
-    println!("{}", cli.output);
+    img
+}
+
+/// Parse the font file read from `path`, exiting if it is not a font.
+fn load_font<'a>(path: &str, data: &'a [u8]) -> FontRef<'a> {
+    FontRef::try_from_slice(data).unwrap_or_else(|_| {
+        eprintln!("Not a TrueType or OpenType font: {}", path);
+        std::process::exit(1);
+    })
+}
+
+/// `out.png` -> `out-<n>.png`
+fn numbered_path(output: &str, n: usize) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("out");
+    let name = match path.extension().and_then(|e| e.to_str()) {
+        Some(ext) => format!("{}-{}.{}", stem, n, ext),
+        None => format!("{}-{}", stem, n),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn widths(specs: &[&str], headers: &[&str]) -> Vec<Option<usize>> {
+        let specs: Vec<String> = specs.iter().map(|s| s.to_string()).collect();
+        let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+        max_widths(&specs, &headers)
+    }
+
+    #[test]
+    fn max_col_widths() {
+        let headers = ["Name", "Description", "Notes"];
+        assert_eq!(widths(&[], &headers), [None, None, None]);
+        assert_eq!(widths(&["40"], &headers), [Some(40); 3]);
+        assert_eq!(
+            widths(&["40", "Description=60"], &headers),
+            [Some(40), Some(60), Some(40)]
+        );
+        // Per-column widths win whatever the order, by number or header
+        assert_eq!(
+            widths(&["3=20", "40"], &headers),
+            [Some(40), Some(40), Some(20)]
+        );
+        // Unknown columns only warn
+        assert_eq!(
+            widths(&["Missing=10", "9=10"], &headers),
+            [None, None, None]
+        );
+    }
 }
@@ -0,0 +1,310 @@
+use image::Rgb;
+use regex::Regex;
+
+/// A numeric comparison from a rule's `lt`, `le`, `gt`, `ge`, `eq` or `ne`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// A condition a cell's text must meet.
+#[derive(Debug)]
+enum Test {
+    /// The regex matches somewhere in the text
+    Match(Regex),
+    /// The text reads as a number and compares true against the value
+    Compare(Op, f64),
+}
+
+/// Colors and weight a rule gives the cells it matches.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CellStyle {
+    pub fg: Option<Rgb<u8>>,
+    pub bg: Option<Rgb<u8>>,
+    pub bold: bool,
+}
+
+/// A conditional style such as `col=Status,match=^Fail,bg=#5a1e1e`: cells
+/// of the column (or of every column, without `col`) that pass all the
+/// tests get the style.
+#[derive(Debug)]
+pub struct Rule {
+    /// 1-based column number or header text
+    pub column: Option<String>,
+    tests: Vec<Test>,
+    style: CellStyle,
+}
+
+const KEYS: [&str; 11] = [
+    "col", "match", "lt", "le", "gt", "ge", "eq", "ne", "fg", "bg", "bold",
+];
+
+/// The number `cell` reads as, allowing a sign, thousands separators, a
+/// decimal point and a leading currency sign or trailing percent sign.
+pub fn number(cell: &str) -> Option<f64> {
+    let s = cell.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    let s = s.strip_prefix(['$', '€', '£', '¥']).unwrap_or(s);
+    let s = s.strip_suffix('%').unwrap_or(s);
+    let digits: String = s.chars().filter(|&c| c != ',').collect();
+    // Only plain decimals: `parse` alone also takes `inf`, `NaN` and `1e5`
+    let plain = digits.chars().all(|c| c.is_ascii_digit() || c == '.');
+    if !plain || !digits.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let n = digits.parse::<f64>().ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// `#rrggbb` or `#rgb`, with or without the `#`.
+fn parse_color(value: &str) -> Result<Rgb<u8>, String> {
+    let hex = value.strip_prefix('#').unwrap_or(value);
+    let digits: Vec<u8> = hex
+        .chars()
+        .map(|c| c.to_digit(16).map(|d| d as u8))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("'{}' is not a hex color", value))?;
+    match digits[..] {
+        [r, g, b] => Ok(Rgb([r * 17, g * 17, b * 17])),
+        [r1, r2, g1, g2, b1, b2] => Ok(Rgb([r1 * 16 + r2, g1 * 16 + g2, b1 * 16 + b2])),
+        _ => Err(format!("'{}' is not a hex color", value)),
+    }
+}
+
+/// Split `spec` into `key=value` fields at commas. Inside a `match=` value
+/// a comma that does not start another `key=` field belongs to the regex,
+/// so `match=^(a,b)$` needs no escaping. Any other fragment is a field of
+/// its own, and an unknown key is reported rather than absorbed.
+fn fields(spec: &str) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for part in spec.split(',') {
+        let (key, value) = part.split_once('=').unwrap_or((part, ""));
+        let key = key.trim();
+        let starts_field = KEYS.contains(&key)
+            || (part.contains('=')
+                && !key.is_empty()
+                && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        match fields.last_mut() {
+            Some((previous_key, previous)) if previous_key == "match" && !starts_field => {
+                previous.push(',');
+                previous.push_str(part);
+            }
+            _ => fields.push((key.to_string(), value.to_string())),
+        }
+    }
+    fields
+}
+
+impl Rule {
+    pub fn parse(spec: &str) -> Result<Rule, String> {
+        let mut rule = Rule {
+            column: None,
+            tests: Vec::new(),
+            style: CellStyle::default(),
+        };
+        for (key, value) in fields(spec) {
+            let compare = |op| {
+                number(&value)
+                    .map(|n| Test::Compare(op, n))
+                    .ok_or_else(|| format!("{}={} needs a number", key, value))
+            };
+            match key.as_str() {
+                "col" => rule.column = Some(value.trim().to_string()),
+                "match" => rule.tests.push(Test::Match(
+                    Regex::new(&value).map_err(|e| format!("invalid regex: {}", e))?,
+                )),
+                "lt" => rule.tests.push(compare(Op::Lt)?),
+                "le" => rule.tests.push(compare(Op::Le)?),
+                "gt" => rule.tests.push(compare(Op::Gt)?),
+                "ge" => rule.tests.push(compare(Op::Ge)?),
+                "eq" => rule.tests.push(compare(Op::Eq)?),
+                "ne" => rule.tests.push(compare(Op::Ne)?),
+                "fg" => rule.style.fg = Some(parse_color(value.trim())?),
+                "bg" => rule.style.bg = Some(parse_color(value.trim())?),
+                "bold" => rule.style.bold = !matches!(value.trim(), "false" | "no" | "0"),
+                _ => {
+                    return Err(format!(
+                        "unknown key '{}'; expected one of {}",
+                        key,
+                        KEYS.join(", ")
+                    ))
+                }
+            }
+        }
+        if rule.style == CellStyle::default() {
+            return Err("the rule sets no style; give fg, bg or bold".to_string());
+        }
+        Ok(rule)
+    }
+
+    /// Index of the rule's column among `headers`, `Some(None)` for a rule
+    /// on every column, or `None` when no column matches.
+    pub fn column_index(&self, headers: &[String]) -> Option<Option<usize>> {
+        let Some(column) = &self.column else {
+            return Some(None);
+        };
+        let index = match column.parse::<usize>() {
+            Ok(n) if (1..=headers.len()).contains(&n) => Some(n - 1),
+            Ok(_) => None,
+            Err(_) => headers.iter().position(|h| h == column),
+        };
+        index.map(Some)
+    }
+
+    fn matches(&self, text: &str) -> bool {
+        self.tests.iter().all(|test| match test {
+            Test::Match(regex) => regex.is_match(text),
+            Test::Compare(op, value) => number(text).is_some_and(|n| match op {
+                Op::Lt => n < *value,
+                Op::Le => n <= *value,
+                Op::Gt => n > *value,
+                Op::Ge => n >= *value,
+                Op::Eq => n == *value,
+                Op::Ne => n != *value,
+            }),
+        })
+    }
+}
+
+/// Style of the cell with `text` in column `index`, where `columns` holds
+/// each rule's column as `Rule::column_index` resolves it. Every matching
+/// rule applies in order, so later rules override the colors of earlier
+/// ones.
+pub fn cell_style(
+    rules: &[Rule],
+    columns: &[Option<Option<usize>>],
+    index: usize,
+    text: &str,
+) -> CellStyle {
+    let mut style = CellStyle::default();
+    for (rule, column) in rules.iter().zip(columns) {
+        let in_column = column.is_some_and(|c| c.is_none_or(|c| c == index));
+        if in_column && rule.matches(text) {
+            style.fg = rule.style.fg.or(style.fg);
+            style.bg = rule.style.bg.or(style.bg);
+            style.bold |= rule.style.bold;
+        }
+    }
+    style
+}
+
+/// Rules from a rules file: one per line, skipping blank lines and lines
+/// starting with `#`. Errors name the line.
+pub fn parse_file(input: &str) -> Result<Vec<Rule>, String> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+        .map(|(i, line)| Rule::parse(line.trim()).map_err(|e| format!("line {}: {}", i + 1, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn style(specs: &[&str], headers: &[&str], index: usize, text: &str) -> CellStyle {
+        let rules: Vec<Rule> = specs.iter().map(|s| Rule::parse(s).unwrap()).collect();
+        let headers: Vec<String> = headers.iter().map(|h| h.to_string()).collect();
+        let columns: Vec<_> = rules.iter().map(|r| r.column_index(&headers)).collect();
+        cell_style(&rules, &columns, index, text)
+    }
+
+    #[test]
+    fn regex_rule_on_a_named_column() {
+        let spec = "col=Status,match=^Fail,bg=#5a1e1e,fg=#ff8a8a";
+        let failed = style(&[spec], &["Test", "Status"], 1, "Failed twice");
+        assert_eq!(failed.bg, Some(Rgb([0x5a, 0x1e, 0x1e])));
+        assert_eq!(failed.fg, Some(Rgb([0xff, 0x8a, 0x8a])));
+        assert_eq!(
+            style(&[spec], &["Test", "Status"], 1, "Pass"),
+            CellStyle::default()
+        );
+        assert_eq!(
+            style(&[spec], &["Test", "Status"], 0, "Fail"),
+            CellStyle::default()
+        );
+    }
+
+    #[test]
+    fn numeric_comparisons() {
+        let spec = "col=2,ge=50,lt=80%,fg=#fa0";
+        let headers = ["name", "coverage"];
+        assert_eq!(
+            style(&[spec], &headers, 1, "64.5%").fg,
+            Some(Rgb([255, 170, 0]))
+        );
+        assert_eq!(style(&[spec], &headers, 1, "80%").fg, None);
+        assert_eq!(style(&[spec], &headers, 1, "n/a").fg, None);
+        assert!(style(&["lt=0,bold"], &headers, 0, "-$1,200").bold);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(number("1,234.5"), Some(1234.5));
+        assert_eq!(number(" -$1,200 "), Some(-1200.0));
+        assert_eq!(number("+48%"), Some(48.0));
+        assert_eq!(number(".5"), Some(0.5));
+        for text in [
+            "inf",
+            "-infinity",
+            "NaN",
+            "1e5",
+            "0x10",
+            "1.2.3",
+            ".",
+            ",",
+            "",
+        ] {
+            assert_eq!(number(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn later_rules_override_earlier_ones() {
+        let specs = ["bg=#111", "match=x,bg=#222,fg=#333"];
+        let matched = style(&specs, &["a"], 0, "x");
+        assert_eq!(matched.bg, Some(Rgb([0x22, 0x22, 0x22])));
+        assert_eq!(
+            style(&specs, &["a"], 0, "y").bg,
+            Some(Rgb([0x11, 0x11, 0x11]))
+        );
+    }
+
+    #[test]
+    fn commas_inside_regexes() {
+        let spec = "match=^(a,b)$,fg=#fff";
+        assert!(style(&[spec], &["c"], 0, "a,b").fg.is_some());
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert!(Rule::parse("col=Status,match=(,fg=#fff").is_err());
+        assert!(Rule::parse("lt=ten,fg=#fff").is_err());
+        assert!(Rule::parse("match=x,fg=red").is_err());
+        assert!(Rule::parse("match=x").is_err());
+        assert!(Rule::parse("colour=x,fg=#fff").is_err());
+        // A mistyped key after a regex is an error, not part of the regex
+        assert!(Rule::parse("match=^Fail,colour=#f00,bg=#300").is_err());
+        assert!(Rule::parse("fg=#fff,#000").is_err());
+    }
+
+    #[test]
+    fn rules_file() {
+        let rules = parse_file(
+            "# status\ncol=Status,match=^Pass,fg=#8f8\n\n  col=Status,match=^Fail,fg=#f88\n",
+        )
+        .unwrap();
+        assert_eq!(rules.len(), 2);
+        let error = parse_file("fg=#fff\nbogus\n").unwrap_err();
+        assert!(error.starts_with("line 2:"));
+    }
+}
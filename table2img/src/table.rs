@@ -0,0 +1,374 @@
+/// Horizontal alignment of a column, from the `:---`, `:---:` and `---:`
+/// markers in the delimiter row.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+/// A parsed table. Every row has exactly as many cells as the header.
+#[derive(Debug, PartialEq)]
+pub struct Table {
+    pub headers: Vec<String>,
+    /// Alignment marker per column, `None` where the column has none
+    pub aligns: Vec<Option<Align>>,
+    pub rows: Vec<Vec<String>>,
+    /// Rows that had to be padded or truncated to the header's width
+    pub warnings: Vec<String>,
+}
+
+/// Split a table row into trimmed cells. Leading and trailing pipes are
+/// optional, `\|` is a literal pipe, and, unlike GFM proper, pipes inside
+/// backtick code spans do not end the cell.
+fn split_row(line: &str) -> Vec<String> {
+    let line = line.trim();
+    let line = line.strip_prefix('|').unwrap_or(line);
+    let chars: Vec<char> = line.chars().collect();
+
+    let mut cells = Vec::new();
+    let mut cell = String::new();
+    let mut ended_with_pipe = false;
+    let mut i = 0;
+    while i < chars.len() {
+        ended_with_pipe = false;
+        match chars[i] {
+            '\\' if chars.get(i + 1) == Some(&'|') => {
+                cell.push('|');
+                i += 2;
+            }
+            // Keep other escapes for the inline parser, including `\\` so
+            // that the pipe after it still separates cells
+            '\\' => {
+                cell.extend(&chars[i..(i + 2).min(chars.len())]);
+                i += 2;
+            }
+            '`' => {
+                let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+                match closing_backticks(&chars, i + run, run) {
+                    Some(end) => {
+                        let span: String = chars[i..end + run].iter().collect();
+                        cell.push_str(&span.replace("\\|", "|"));
+                        i = end + run;
+                    }
+                    None => {
+                        cell.extend(&chars[i..i + run]);
+                        i += run;
+                    }
+                }
+            }
+            '|' => {
+                cells.push(cell.trim().to_string());
+                cell.clear();
+                ended_with_pipe = true;
+                i += 1;
+            }
+            c => {
+                cell.push(c);
+                i += 1;
+            }
+        }
+    }
+    if !ended_with_pipe {
+        cells.push(cell.trim().to_string());
+    }
+    cells
+}
+
+/// Start of the first run of exactly `len` backticks at or after `from`.
+fn closing_backticks(chars: &[char], from: usize, len: usize) -> Option<usize> {
+    let mut i = from;
+    while i < chars.len() {
+        if chars[i] == '`' {
+            let run = chars[i..].iter().take_while(|&&c| c == '`').count();
+            if run == len {
+                return Some(i);
+            }
+            i += run;
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
+
+/// Alignment markers of a delimiter row (e.g. `|:---|:---:|---:|`), or
+/// `None` if `line` is not one.
+fn parse_delimiter(line: &str) -> Option<Vec<Option<Align>>> {
+    let cells = split_row(line);
+    let is_marker = |c: &str| {
+        let dashes = c.strip_prefix(':').unwrap_or(c);
+        let dashes = dashes.strip_suffix(':').unwrap_or(dashes);
+        !dashes.is_empty() && dashes.chars().all(|ch| ch == '-')
+    };
+    if !cells.iter().all(|c| is_marker(c)) {
+        return None;
+    }
+    Some(
+        cells
+            .iter()
+            .map(|c| match (c.starts_with(':'), c.ends_with(':')) {
+                (true, true) => Some(Align::Center),
+                (false, true) => Some(Align::Right),
+                (true, false) => Some(Align::Left),
+                (false, false) => None,
+            })
+            .collect(),
+    )
+}
+
+/// Lines indented four or more columns are code, not table rows.
+fn is_indented_code(line: &str) -> bool {
+    line.chars()
+        .take_while(|c| *c == ' ' || *c == '\t')
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum::<usize>()
+        >= 4
+}
+
+/// Whether `line` starts a block that ends the table body: a blank line,
+/// block quote, ATX heading, code fence or thematic break.
+fn ends_table(line: &str) -> bool {
+    let t = line.trim();
+    let hashes = t.chars().take_while(|&c| c == '#').count();
+    let heading =
+        (1..=6).contains(&hashes) && t[hashes..].chars().next().is_none_or(char::is_whitespace);
+    let rule = !t.contains('|')
+        && ['-', '*', '_'].iter().any(|&m| {
+            t.chars().filter(|&c| c == m).count() >= 3
+                && t.chars().all(|c| c == m || c.is_whitespace())
+        });
+    t.is_empty()
+        || t.starts_with('>')
+        || heading
+        || t.starts_with("```")
+        || t.starts_with("~~~")
+        || rule
+}
+
+/// Try to read a table whose header row is `lines[start]`. Returns the table
+/// and the index of the first line after it.
+fn parse_at(lines: &[&str], start: usize) -> Option<(Table, usize)> {
+    let header_line = *lines.get(start)?;
+    let delimiter_line = *lines.get(start + 1)?;
+    if header_line.trim().is_empty() || is_indented_code(header_line) {
+        return None;
+    }
+    // Without any pipe, `abc` over `---` is a setext heading
+    if !header_line.contains('|') && !delimiter_line.contains('|') {
+        return None;
+    }
+    let aligns = parse_delimiter(delimiter_line)?;
+    let headers = split_row(header_line);
+    if headers.len() != aligns.len() {
+        return None;
+    }
+
+    let mut rows = Vec::new();
+    let mut warnings = Vec::new();
+    let mut next = start + 2;
+    while let Some(line) = lines.get(next) {
+        if ends_table(line) {
+            break;
+        }
+        let mut cells = split_row(line);
+        if cells.len() != headers.len() {
+            warnings.push(format!(
+                "row {} has {} cells but the header has {}; {}",
+                rows.len() + 1,
+                cells.len(),
+                headers.len(),
+                if cells.len() < headers.len() {
+                    "padding with empty cells"
+                } else {
+                    "ignoring the extra cells"
+                }
+            ));
+            cells.resize(headers.len(), String::new());
+        }
+        rows.push(cells);
+        next += 1;
+    }
+
+    Some((
+        Table {
+            headers,
+            aligns,
+            rows,
+            warnings,
+        },
+        next,
+    ))
+}
+
+/// The character and length of the code fence `line` opens, if any.
+fn opening_fence(line: &str) -> Option<(char, usize)> {
+    if is_indented_code(line) {
+        return None;
+    }
+    let t = line.trim_start();
+    let marker = t.chars().next().filter(|c| *c == '`' || *c == '~')?;
+    let len = t.chars().take_while(|&c| c == marker).count();
+    // A backtick fence's info string cannot contain backticks
+    let valid = len >= 3 && (marker == '~' || !t[len..].contains('`'));
+    valid.then_some((marker, len))
+}
+
+/// Whether `line` closes a fence opened with `len` `marker` characters.
+fn closes_fence(line: &str, marker: char, len: usize) -> bool {
+    let t = line.trim();
+    !is_indented_code(line)
+        && t.chars().take_while(|&c| c == marker).count() >= len
+        && t.chars().all(|c| c == marker)
+}
+
+/// Every GFM table in the markdown document `input`, in order, skipping
+/// fenced code blocks.
+pub fn parse_tables(input: &str) -> Vec<Table> {
+    let lines: Vec<&str> = input.lines().collect();
+    let mut tables = Vec::new();
+    let mut fence = None;
+    let mut i = 0;
+    while i < lines.len() {
+        if let Some((marker, len)) = fence {
+            if closes_fence(lines[i], marker, len) {
+                fence = None;
+            }
+            i += 1;
+        } else if let Some(open) = opening_fence(lines[i]) {
+            fence = Some(open);
+            i += 1;
+        } else if let Some((table, next)) = parse_at(&lines, i) {
+            tables.push(table);
+            i = next;
+        } else {
+            i += 1;
+        }
+    }
+    tables
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(row: &[&str]) -> Vec<String> {
+        row.iter().map(|c| c.to_string()).collect()
+    }
+
+    fn parse_table(input: &str) -> Option<Table> {
+        parse_tables(input).into_iter().next()
+    }
+
+    // Examples from the "Tables (extension)" section of the GFM spec
+
+    #[test]
+    fn basic_table() {
+        let table = parse_table("| foo | bar |\n| --- | --- |\n| baz | bim |").unwrap();
+        assert_eq!(table.headers, cells(&["foo", "bar"]));
+        assert_eq!(table.aligns, vec![None, None]);
+        assert_eq!(table.rows, vec![cells(&["baz", "bim"])]);
+        assert!(table.warnings.is_empty());
+    }
+
+    #[test]
+    fn alignment_and_uneven_widths() {
+        let table = parse_table("| abc | defghi |\n:-: | -----------:\nbar | baz").unwrap();
+        assert_eq!(table.headers, cells(&["abc", "defghi"]));
+        assert_eq!(table.aligns, vec![Some(Align::Center), Some(Align::Right)]);
+        assert_eq!(table.rows, vec![cells(&["bar", "baz"])]);
+    }
+
+    #[test]
+    fn escaped_pipes() {
+        let input = "| f\\|oo  |\n| ------ |\n| b `\\|` az |\n| b **\\|** im |";
+        let table = parse_table(input).unwrap();
+        assert_eq!(table.headers, cells(&["f|oo"]));
+        assert_eq!(
+            table.rows,
+            vec![cells(&["b `|` az"]), cells(&["b **|** im"])]
+        );
+    }
+
+    #[test]
+    fn block_quote_ends_table() {
+        let table = parse_table("| abc | def |\n| --- | --- |\n| bar | baz |\n> bar").unwrap();
+        assert_eq!(table.rows, vec![cells(&["bar", "baz"])]);
+    }
+
+    #[test]
+    fn row_without_pipes_continues_until_blank_line() {
+        let input = "| abc | def |\n| --- | --- |\n| bar | baz |\nbar\n\nbar";
+        let table = parse_table(input).unwrap();
+        assert_eq!(
+            table.rows,
+            vec![cells(&["bar", "baz"]), cells(&["bar", ""])]
+        );
+    }
+
+    #[test]
+    fn header_and_delimiter_must_match() {
+        assert_eq!(parse_table("| abc | def |\n| --- |\n| bar |"), None);
+    }
+
+    #[test]
+    fn ragged_rows_are_padded_or_truncated() {
+        let input = "| abc | def |\n| --- | --- |\n| bar |\n| bar | baz | boo |";
+        let table = parse_table(input).unwrap();
+        assert_eq!(
+            table.rows,
+            vec![cells(&["bar", ""]), cells(&["bar", "baz"])]
+        );
+        assert_eq!(table.warnings.len(), 2);
+    }
+
+    #[test]
+    fn header_only_table() {
+        let table = parse_table("| abc | def |\n| --- | --- |").unwrap();
+        assert_eq!(table.headers, cells(&["abc", "def"]));
+        assert!(table.rows.is_empty());
+    }
+
+    // Cases beyond the spec examples
+
+    #[test]
+    fn pipes_inside_code_spans() {
+        let table = parse_table("| a | b |\n|---|---|\n| `x | y` | z |").unwrap();
+        assert_eq!(table.rows, vec![cells(&["`x | y`", "z"])]);
+    }
+
+    #[test]
+    fn dash_rows_are_data() {
+        let table = parse_table("| a | b |\n|---|---|\n| - | - |").unwrap();
+        assert_eq!(table.rows, vec![cells(&["-", "-"])]);
+    }
+
+    #[test]
+    fn setext_heading_is_not_a_table() {
+        assert_eq!(parse_table("abc\n---"), None);
+    }
+
+    #[test]
+    fn table_after_text() {
+        let table = parse_table("Intro text.\n\n| a |\n|:--|\n| 1 |").unwrap();
+        assert_eq!(table.aligns, vec![Some(Align::Left)]);
+        assert_eq!(table.rows, vec![cells(&["1"])]);
+    }
+
+    #[test]
+    fn every_table_in_a_document() {
+        let input = "# Doc\n\n| a |\n|---|\n| 1 |\n\nText.\n\n| b | c |\n|---|---|\n| 2 | 3 |\n";
+        let tables = parse_tables(input);
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].headers, cells(&["a"]));
+        assert_eq!(tables[1].rows, vec![cells(&["2", "3"])]);
+    }
+
+    #[test]
+    fn tables_in_fenced_code_are_ignored() {
+        let input = "````md\n| a |\n|---|\n```\n| b |\n|---|\n````\n\n~~~\n| c |\n|---|\n~~~\n| d |\n|---|\n";
+        let tables = parse_tables(input);
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0].headers, cells(&["d"]));
+    }
+}